@@ -3,7 +3,7 @@ use super::{
 	misc::{ValueTypeBuilder, ValueTypesBuilder},
 };
 use crate::elements;
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 
 /// Signature template description
 pub enum Signature {
@@ -279,17 +279,43 @@ where
 	}
 
 	/// Set code of the function
-	pub fn with_instructions(mut self, instructions: elements::Instructions) -> Self {
+	///
+	/// Final `end` instruction is added unless the function body is already closed.
+	pub fn with_instructions(mut self, mut instructions: elements::Instructions) -> Self {
+		terminate(instructions.elements_mut());
 		*self.body.code_mut() = instructions;
 		self
 	}
 
+	/// Set code of the function, copied from the slice
+	///
+	/// Final `end` instruction is added unless the function body is already closed.
+	pub fn with_instruction_slice(self, instructions: &[elements::Instruction]) -> Self {
+		self.with_instructions(elements::Instructions::new(instructions.to_vec()))
+	}
+
 	/// Finish current builder spawning resulting struct
 	pub fn build(self) -> F::Result {
 		self.callback.invoke(self.body)
 	}
 }
 
+/// Append `end` if it is needed to close the function body.
+fn terminate(instructions: &mut Vec<elements::Instruction>) {
+	let depth = instructions.iter().fold(1usize, |depth, instruction| {
+		if instruction.is_block() {
+			depth + 1
+		} else if instruction.is_terminal() {
+			depth.saturating_sub(1)
+		} else {
+			depth
+		}
+	});
+	if depth > 0 {
+		instructions.push(elements::Instruction::End);
+	}
+}
+
 /// Function definition (extended structure to specify function entirely, incl. signature, mainness and code)
 pub struct FunctionDefinition {
 	/// Is this function is start function
@@ -298,6 +324,9 @@ pub struct FunctionDefinition {
 	pub signature: Signature,
 	/// Body (code) of the function
 	pub code: elements::FuncBody,
+
+	/// Names of the locals (including parameters), set with `FunctionBuilder::with_local_names`
+	pub(crate) local_names: Vec<String>,
 }
 
 impl Default for FunctionDefinition {
@@ -306,6 +335,7 @@ impl Default for FunctionDefinition {
 			is_main: false,
 			signature: Signature::TypeReference(0),
 			code: elements::FuncBody::empty(),
+			local_names: Vec::new(),
 		}
 	}
 }
@@ -366,6 +396,14 @@ where
 		self
 	}
 
+	/// Name the locals (including parameters) of this function, in index order
+	///
+	/// The names are emitted into the local names of the module's name section.
+	pub fn with_local_names(mut self, names: &[&str]) -> Self {
+		self.func.local_names = names.iter().map(|&name| name.into()).collect();
+		self
+	}
+
 	/// Finalize current builder spawning resulting struct in the callback
 	pub fn build(self) -> F::Result {
 		self.callback.invoke(self.func)
@@ -483,4 +521,39 @@ mod tests {
 		assert_eq!(func.code.locals().len(), 0);
 		assert_eq!(func.code.code().elements().len(), 1);
 	}
+
+	#[test]
+	fn func_instructions_terminated() {
+		use elements::{BlockType, Instruction::*};
+
+		let code = [Block(BlockType::NoResult), Nop, End, End];
+		let func = function().body().with_instruction_slice(&code).build().build();
+		assert_eq!(func.code.code().elements(), &code);
+
+		let func = function()
+			.body()
+			.with_instructions(elements::Instructions::new(code.to_vec()))
+			.build()
+			.build();
+		assert_eq!(func.code.code().elements(), &code);
+	}
+
+	#[test]
+	fn func_instructions_unterminated() {
+		use elements::{BlockType, Instruction::*};
+
+		let func = function()
+			.body()
+			.with_instruction_slice(&[Block(BlockType::NoResult), Nop, End])
+			.build()
+			.build();
+		assert_eq!(func.code.code().elements(), &[Block(BlockType::NoResult), Nop, End, End]);
+
+		let func = function()
+			.body()
+			.with_instructions(elements::Instructions::new(vec![Nop]))
+			.build()
+			.build();
+		assert_eq!(func.code.code().elements(), &[Nop, End]);
+	}
 }
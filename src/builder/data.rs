@@ -9,6 +9,8 @@ pub struct DataSegmentBuilder<F = Identity> {
 	mem_index: u32,
 	offset: elements::InitExpr,
 	value: Vec<u8>,
+	#[cfg(feature = "bulk")]
+	passive: bool,
 }
 
 impl DataSegmentBuilder {
@@ -32,6 +34,8 @@ impl<F> DataSegmentBuilder<F> {
 			mem_index: 0,
 			offset: elements::InitExpr::empty(),
 			value: Vec::new(),
+			#[cfg(feature = "bulk")]
+			passive: false,
 		}
 	}
 
@@ -41,6 +45,13 @@ impl<F> DataSegmentBuilder<F> {
 		self
 	}
 
+	/// Make the segment passive, i.e. without offset, to be used by `memory.init`
+	#[cfg(feature = "bulk")]
+	pub fn passive(mut self) -> Self {
+		self.passive = true;
+		self
+	}
+
 	/// Set the bytes value of the segment
 	pub fn value(mut self, value: Vec<u8>) -> Self {
 		self.value = value;
@@ -54,6 +65,13 @@ where
 {
 	/// Finish current builder, spawning resulting struct
 	pub fn build(self) -> F::Result {
+		#[cfg(feature = "bulk")]
+		if self.passive {
+			let mut segment = elements::DataSegment::new(self.mem_index, None, self.value);
+			segment.set_passive(true);
+			return self.callback.invoke(segment)
+		}
+
 		self.callback.invoke(elements::DataSegment::new(
 			self.mem_index,
 			Some(self.offset),
@@ -49,12 +49,24 @@ impl<F> GlobalBuilder<F> {
 		self
 	}
 
+	/// Set/override mutability
+	pub fn with_mutability(mut self, is_mutable: bool) -> Self {
+		self.is_mutable = is_mutable;
+		self
+	}
+
 	/// Set initialization expression instruction for this global (`end` instruction will be added automatically)
 	pub fn init_expr(mut self, instruction: elements::Instruction) -> Self {
 		self.init_expr = elements::InitExpr::new(vec![instruction, elements::Instruction::End]);
 		self
 	}
 
+	/// Set/override the whole initialization expression (including the final `end`)
+	pub fn with_init_expr(mut self, init_expr: elements::InitExpr) -> Self {
+		self.init_expr = init_expr;
+		self
+	}
+
 	/// Start value type builder
 	pub fn value_type(self) -> ValueTypeBuilder<Self> {
 		ValueTypeBuilder::with_callback(self)
@@ -72,6 +84,16 @@ where
 			self.init_expr,
 		))
 	}
+
+	/// Finalize current builder, checking that a constant initialization expression
+	/// has the declared value type
+	pub fn try_build(self) -> Result<F::Result, elements::Error> {
+		match self.init_expr.eval() {
+			Some(value) if value.value_type() != self.value_type =>
+				Err(elements::Error::Other("Global initializer does not match its value type")),
+			_ => Ok(self.build()),
+		}
+	}
 }
 
 impl<F> Invoke<elements::ValueType> for GlobalBuilder<F> {
@@ -97,4 +119,28 @@ mod tests {
 		assert_eq!(entry.global_type().content_type(), elements::ValueType::I32);
 		assert!(!entry.global_type().is_mutable());
 	}
+
+	#[test]
+	fn try_build() {
+		let init_expr = elements::InitExpr::new(vec![
+			elements::Instruction::I32Const(42),
+			elements::Instruction::End,
+		]);
+
+		let entry = global()
+			.with_type(elements::ValueType::I32)
+			.with_mutability(false)
+			.with_init_expr(init_expr.clone())
+			.try_build()
+			.expect("Should be built");
+		assert_eq!(entry.global_type().content_type(), elements::ValueType::I32);
+		assert!(!entry.global_type().is_mutable());
+		assert_eq!(entry.init_expr(), &init_expr);
+
+		assert!(global()
+			.with_type(elements::ValueType::I64)
+			.with_init_expr(init_expr)
+			.try_build()
+			.is_err());
+	}
 }
@@ -57,6 +57,28 @@ impl<F> ImportBuilder<F> {
 		self
 	}
 
+	/// Import a function with the given type reference
+	pub fn external_func(self, type_index: u32) -> Self {
+		self.with_external(elements::External::Function(type_index))
+	}
+
+	/// Import a global of the given type and mutability
+	pub fn external_global(self, value_type: elements::ValueType, mutable: bool) -> Self {
+		self.with_external(elements::External::Global(elements::GlobalType::new(
+			value_type, mutable,
+		)))
+	}
+
+	/// Import a linear memory with the given limits
+	pub fn external_memory(self, limits: elements::ResizableLimits) -> Self {
+		self.with_external(elements::External::Memory(limits.into()))
+	}
+
+	/// Import a table of the given type
+	pub fn external_table(self, table_type: elements::TableType) -> Self {
+		self.with_external(elements::External::Table(table_type))
+	}
+
 	/// Start new external mapping builder
 	pub fn external(self) -> ImportExternalBuilder<Self> {
 		ImportExternalBuilder::with_callback(self)
@@ -129,6 +151,7 @@ pub fn import() -> ImportBuilder {
 #[cfg(test)]
 mod tests {
 	use super::import;
+	use crate::elements::{serialize, ResizableLimits, TableType, ValueType};
 
 	#[test]
 	fn example() {
@@ -138,4 +161,20 @@ mod tests {
 		assert_eq!(entry.module(), "env");
 		assert_eq!(entry.field(), "memory");
 	}
+
+	#[test]
+	fn external_kinds() {
+		let func = import().path("env", "f").external_func(3).build();
+		assert_eq!(serialize(func).unwrap(), [3, b'e', b'n', b'v', 1, b'f', 0x00, 3]);
+
+		let table = import().path("env", "t").external_table(TableType::new(1, None)).build();
+		assert_eq!(serialize(table).unwrap(), [3, b'e', b'n', b'v', 1, b't', 0x01, 0x70, 0, 1]);
+
+		let limits = ResizableLimits::new(1, Some(2));
+		let memory = import().path("env", "m").external_memory(limits).build();
+		assert_eq!(serialize(memory).unwrap(), [3, b'e', b'n', b'v', 1, b'm', 0x02, 1, 1, 2]);
+
+		let global = import().path("env", "g").external_global(ValueType::I64, true).build();
+		assert_eq!(serialize(global).unwrap(), [3, b'e', b'n', b'v', 1, b'g', 0x03, 0x7e, 1]);
+	}
 }
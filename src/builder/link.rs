@@ -0,0 +1,371 @@
+use crate::elements::{
+	self, External, ImportCountType, IndexMap, Instruction, InstructionVisitor, Internal, Module,
+	Section, Type,
+};
+use alloc::{format, string::String, vec::Vec};
+use core::mem;
+
+/// Mapping of one index space of a linked module into the merged one.
+struct Space {
+	imported: u32,
+	import_base: u32,
+	defined_base: u32,
+}
+
+impl Space {
+	fn map(&self, index: u32) -> u32 {
+		if index < self.imported {
+			self.import_base + index
+		} else {
+			self.defined_base + (index - self.imported)
+		}
+	}
+
+	fn map_keys<T>(&self, map: &mut IndexMap<T>) {
+		let entries = mem::replace(map, IndexMap::with_capacity(0));
+		*map = entries.into_iter().map(|(index, value)| (self.map(index), value)).collect();
+	}
+}
+
+/// Index rewrites for one of the linked modules.
+struct Relocation {
+	types: Vec<u32>,
+	functions: Space,
+	globals: Space,
+	#[cfg(feature = "bulk")]
+	element_base: u32,
+	#[cfg(feature = "bulk")]
+	data_base: u32,
+}
+
+impl Relocation {
+	fn map_type(&self, type_ref: u32) -> u32 {
+		self.types.get(type_ref as usize).copied().unwrap_or(type_ref)
+	}
+
+	fn apply(&mut self, module: &mut Module) {
+		module.visit_instructions(self);
+		for section in module.sections_mut() {
+			match *section {
+				Section::Import(ref mut is) =>
+					for entry in is.entries_mut() {
+						if let External::Function(ref mut type_ref) = *entry.external_mut() {
+							*type_ref = self.map_type(*type_ref);
+						}
+					},
+				Section::Function(ref mut fs) =>
+					for func in fs.entries_mut() {
+						*func.type_ref_mut() = self.map_type(func.type_ref());
+					},
+				Section::Export(ref mut es) =>
+					for entry in es.entries_mut() {
+						match *entry.internal_mut() {
+							Internal::Function(ref mut index) =>
+								*index = self.functions.map(*index),
+							Internal::Global(ref mut index) => *index = self.globals.map(*index),
+							_ => {},
+						}
+					},
+				Section::Element(ref mut es) =>
+					for segment in es.entries_mut() {
+						for member in segment.members_mut() {
+							*member = self.functions.map(*member);
+						}
+					},
+				Section::Start(ref mut index) => *index = self.functions.map(*index),
+				Section::Name(ref mut ns) => {
+					if let Some(ref mut functions) = *ns.functions_mut() {
+						self.functions.map_keys(functions.names_mut());
+					}
+					if let Some(ref mut locals) = *ns.locals_mut() {
+						self.functions.map_keys(locals.local_names_mut());
+					}
+					if let Some(ref mut labels) = *ns.labels_mut() {
+						self.functions.map_keys(labels.label_names_mut());
+					}
+				},
+				_ => {},
+			}
+		}
+	}
+}
+
+impl InstructionVisitor for Relocation {
+	fn visit(&mut self, instr: &mut Instruction) {
+		match *instr {
+			Instruction::Call(ref mut index) => *index = self.functions.map(*index),
+			Instruction::CallIndirect(ref mut type_ref, _) => *type_ref = self.map_type(*type_ref),
+			Instruction::GetGlobal(ref mut index) | Instruction::SetGlobal(ref mut index) =>
+				*index = self.globals.map(*index),
+			#[cfg(feature = "multi_value")]
+			Instruction::Block(elements::BlockType::TypeIndex(ref mut type_ref)) |
+			Instruction::Loop(elements::BlockType::TypeIndex(ref mut type_ref)) |
+			Instruction::If(elements::BlockType::TypeIndex(ref mut type_ref)) =>
+				*type_ref = self.map_type(*type_ref),
+			#[cfg(feature = "bulk")]
+			Instruction::Bulk(ref mut bulk) => match *bulk {
+				elements::BulkInstruction::MemoryInit(ref mut index) |
+				elements::BulkInstruction::MemoryDrop(ref mut index) => *index += self.data_base,
+				elements::BulkInstruction::TableInit(ref mut index) |
+				elements::BulkInstruction::TableDrop(ref mut index) => *index += self.element_base,
+				_ => {},
+			},
+			_ => {},
+		}
+	}
+}
+
+/// Statically link two modules into one.
+///
+/// Imports and definitions of `b` are appended to those of `a`, and all references
+/// are rewritten to the merged index spaces: imports of `b` follow the imports of `a`,
+/// so definitions of `a` are shifted by the number of imports of `b`. Signatures of `b`
+/// reuse identical types of `a`.
+///
+/// Exports of `b` with a name already exported by `a` are prefixed with `b.`. At most
+/// one of the modules may have a start function, a table or a linear memory. Custom
+/// sections of `b` are dropped.
+///
+/// Function, local and label names of `a` are rewritten as well. A name section of `a`
+/// which cannot be parsed is dropped, and so are its relocation sections, whose offsets
+/// into the code no longer hold.
+pub fn link(mut a: Module, mut b: Module) -> Result<Module, elements::Error> {
+	if a.start_section().is_some() && b.start_section().is_some() {
+		return Err(elements::Error::Other("Both linked modules have a start function"))
+	}
+	if a.table_space() > 0 && b.table_space() > 0 {
+		return Err(elements::Error::Other("Both linked modules have a table"))
+	}
+	if a.memory_space() > 0 && b.memory_space() > 0 {
+		return Err(elements::Error::Other("Both linked modules have a linear memory"))
+	}
+	#[cfg(feature = "exceptions")]
+	if b.import_count(ImportCountType::Tag) > 0 || b.tag_section().is_some() {
+		return Err(elements::Error::Other("Linking a module with tags is not supported"))
+	}
+
+	let names = a.names();
+	a.sections_mut().retain(|section| match *section {
+		Section::Name(_) | Section::Reloc(_) => false,
+		Section::Custom(ref custom) =>
+			custom.name() != "name" && !custom.name().starts_with("reloc."),
+		_ => true,
+	});
+	if let Some(names) = names {
+		a.set_names(names);
+	}
+
+	let b_types = b.type_section().map(|ts| ts.types().to_vec()).unwrap_or_default();
+	let types = b_types
+		.into_iter()
+		.map(|Type::Function(func_type)| {
+			let existing = a
+				.type_section()
+				.and_then(|ts| ts.types().iter().position(|Type::Function(ty)| *ty == func_type));
+			match existing {
+				Some(index) => index as u32,
+				None => a.append_type(func_type),
+			}
+		})
+		.collect();
+
+	let a_functions = a.import_count(ImportCountType::Function) as u32;
+	let b_functions = b.import_count(ImportCountType::Function) as u32;
+	let a_globals = a.import_count(ImportCountType::Global) as u32;
+	let b_globals = b.import_count(ImportCountType::Global) as u32;
+
+	Relocation {
+		types: Vec::new(),
+		functions: Space {
+			imported: a_functions,
+			import_base: 0,
+			defined_base: a_functions + b_functions,
+		},
+		globals: Space { imported: a_globals, import_base: 0, defined_base: a_globals + b_globals },
+		#[cfg(feature = "bulk")]
+		element_base: 0,
+		#[cfg(feature = "bulk")]
+		data_base: 0,
+	}
+	.apply(&mut a);
+	Relocation {
+		types,
+		functions: Space {
+			imported: b_functions,
+			import_base: a_functions,
+			defined_base: a.functions_space() as u32 + b_functions,
+		},
+		globals: Space {
+			imported: b_globals,
+			import_base: a_globals,
+			defined_base: a.globals_space() as u32 + b_globals,
+		},
+		#[cfg(feature = "bulk")]
+		element_base: a.element_segment_count() as u32,
+		#[cfg(feature = "bulk")]
+		data_base: a.data_segment_count() as u32,
+	}
+	.apply(&mut b);
+
+	let exported: Vec<String> = a
+		.export_section()
+		.map(|es| es.entries().iter().map(|entry| entry.field().into()).collect())
+		.unwrap_or_default();
+	let has_data_count = a
+		.sections()
+		.iter()
+		.chain(b.sections())
+		.any(|s| matches!(*s, Section::DataCount(_)));
+
+	macro_rules! append {
+		($variant:ident, $section:expr, $section_mut:ident, $entries_mut:ident) => {{
+			let mut section = $section;
+			match a.$section_mut() {
+				Some(existing) => existing.$entries_mut().append(section.$entries_mut()),
+				None => a.insert_section(Section::$variant(section))?,
+			}
+		}};
+	}
+
+	for section in b.into_sections() {
+		match section {
+			Section::Import(is) => append!(Import, is, import_section_mut, entries_mut),
+			Section::Function(fs) => append!(Function, fs, function_section_mut, entries_mut),
+			Section::Table(ts) => append!(Table, ts, table_section_mut, entries_mut),
+			Section::Memory(ms) => append!(Memory, ms, memory_section_mut, entries_mut),
+			Section::Global(gs) => append!(Global, gs, global_section_mut, entries_mut),
+			Section::Export(mut es) => {
+				for entry in es.entries_mut() {
+					if exported.iter().any(|field| field == entry.field()) {
+						*entry.field_mut() = format!("b.{}", entry.field());
+					}
+				}
+				append!(Export, es, export_section_mut, entries_mut)
+			},
+			Section::Start(index) => a.set_start_section(index),
+			Section::Element(es) => append!(Element, es, elements_section_mut, entries_mut),
+			Section::Code(cs) => append!(Code, cs, code_section_mut, bodies_mut),
+			Section::Data(ds) => append!(Data, ds, data_section_mut, entries_mut),
+			_ => {},
+		}
+	}
+
+	if has_data_count {
+		a.ensure_data_count_section();
+	}
+
+	Ok(a)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::link;
+	use crate::{builder, elements};
+
+	#[test]
+	fn call_targets() {
+		use elements::{ExportEntry, Instruction::*, Instructions, Internal, ValueType};
+
+		let a = builder::module()
+			.import()
+			.module("env")
+			.field("log")
+			.external()
+			.func(0)
+			.build()
+			.function()
+			.signature()
+			.with_param(ValueType::I32)
+			.build()
+			.body()
+			.with_instructions(Instructions::new(vec![GetLocal(0), Call(0), Call(1), End]))
+			.build()
+			.build()
+			.with_export(ExportEntry::new("run".into(), Internal::Function(1)))
+			.build();
+		let b = builder::module()
+			.import()
+			.module("env")
+			.field("abort")
+			.external()
+			.func(0)
+			.build()
+			.function()
+			.signature()
+			.with_param(ValueType::I32)
+			.build()
+			.body()
+			.with_instructions(Instructions::new(vec![GetLocal(0), Call(0), Call(1), End]))
+			.build()
+			.build()
+			.with_export(ExportEntry::new("run".into(), Internal::Function(1)))
+			.build();
+
+		let linked = link(a, b).expect("Linking to succeed");
+
+		let imports = linked.import_section().expect("Should have import section").entries();
+		let fields: Vec<&str> = imports.iter().map(|entry| entry.field()).collect();
+		assert_eq!(fields, ["log", "abort"]);
+		assert_eq!(linked.type_section().expect("Should have type section").types().len(), 1);
+
+		let bodies = linked.code_section().expect("Should have code section").bodies();
+		assert_eq!(bodies[0].code().elements(), &[GetLocal(0), Call(0), Call(2), End]);
+		assert_eq!(bodies[1].code().elements(), &[GetLocal(0), Call(1), Call(3), End]);
+
+		let exports = linked.export_section().expect("Should have export section").entries();
+		assert_eq!(exports[0], ExportEntry::new("run".into(), Internal::Function(2)));
+		assert_eq!(exports[1], ExportEntry::new("b.run".into(), Internal::Function(3)));
+
+		let module = elements::Module::from_bytes(linked.into_bytes().expect("Should serialize"))
+			.expect("Should deserialize");
+		assert_eq!(module.functions_space(), 4);
+	}
+
+	#[test]
+	fn conflicts() {
+		let with_memory = || builder::module().memory().build().build();
+		assert!(link(with_memory(), with_memory()).is_err());
+		assert!(link(with_memory(), builder::module().build()).is_ok());
+	}
+
+	#[test]
+	fn names() {
+		use elements::{FunctionNameSubsection, NameSection};
+
+		let mut a = builder::module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.with_locals(vec![elements::Local::new(1, elements::ValueType::I32)])
+			.build()
+			.with_local_names(&["tmp"])
+			.build()
+			.build();
+		let mut functions = FunctionNameSubsection::default();
+		functions.names_mut().insert(0, "a_main".into());
+		let mut names = a.names().expect("Should have name section");
+		*names.functions_mut() = Some(functions);
+		a.set_names(names);
+		a.set_custom_section("reloc.CODE", vec![0x0a, 0x00]);
+		let b = builder::module()
+			.import()
+			.module("env")
+			.field("x")
+			.external()
+			.func(0)
+			.build()
+			.build();
+
+		let linked = link(a, b).expect("Linking to succeed");
+
+		assert!(linked.custom_sections().all(|custom| custom.name() != "reloc.CODE"));
+		let names: NameSection = linked.names().expect("Should have name section");
+		let functions = names.functions().expect("Should have function names").names();
+		assert_eq!(functions.get(0), None);
+		assert_eq!(functions.get(1).map(String::as_str), Some("a_main"));
+		let locals = names.locals().expect("Should have local names").local_names();
+		assert!(locals.get(0).is_none());
+		assert!(locals.get(1).is_some());
+	}
+}
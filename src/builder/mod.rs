@@ -6,6 +6,7 @@ mod export;
 mod global;
 mod import;
 mod invoke;
+mod link;
 mod memory;
 mod misc;
 mod module;
@@ -21,6 +22,7 @@ pub use self::{
 	global::{global, GlobalBuilder},
 	import::{import, ImportBuilder},
 	invoke::Identity,
+	link::link,
 	memory::MemoryBuilder,
 	module::{from_module, module, CodeLocation, ModuleBuilder},
 	table::{TableBuilder, TableDefinition, TableEntryDefinition},
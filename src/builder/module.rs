@@ -6,7 +6,7 @@ use super::{
 	table::{self, TableBuilder},
 };
 use crate::elements;
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 
 /// Module builder
 pub struct ModuleBuilder<F = Identity> {
@@ -36,6 +36,8 @@ struct ModuleScaffold {
 	pub code: elements::CodeSection,
 	pub data: elements::DataSection,
 	pub other: Vec<elements::Section>,
+	/// Local names of defined functions, by index in the code section.
+	pub local_names: Vec<(u32, Vec<String>)>,
 }
 
 impl From<elements::Module> for ModuleScaffold {
@@ -106,6 +108,7 @@ impl From<elements::Module> for ModuleScaffold {
 			code: code.unwrap_or_default(),
 			data: data.unwrap_or_default(),
 			other,
+			local_names: Vec::new(),
 		}
 	}
 }
@@ -113,6 +116,7 @@ impl From<elements::Module> for ModuleScaffold {
 impl From<ModuleScaffold> for elements::Module {
 	fn from(module: ModuleScaffold) -> Self {
 		let mut sections = Vec::new();
+		let imported_functions = module.import.functions() as u32;
 
 		let types = module.types;
 		if !types.types().is_empty() {
@@ -158,7 +162,41 @@ impl From<ModuleScaffold> for elements::Module {
 			sections.push(elements::Section::Data(data));
 		}
 		sections.extend(module.other);
-		elements::Module::new(sections)
+		let mut result = elements::Module::new(sections);
+		if !module.local_names.is_empty() {
+			// Merge into the name section, which may still be a raw custom section. A name
+			// section which cannot be parsed is kept as is, without the local names.
+			result = match result.parse_names() {
+				Ok(mut result) => {
+					let sections = result.sections_mut();
+					if !sections
+						.iter()
+						.any(|section| matches!(*section, elements::Section::Name(_)))
+					{
+						let name_section = elements::NameSection::new(None, None, None);
+						sections.push(elements::Section::Name(name_section));
+					}
+					let name_section = sections
+						.iter_mut()
+						.find_map(|section| match *section {
+							elements::Section::Name(ref mut name_section) => Some(name_section),
+							_ => None,
+						})
+						.expect("name section to exist");
+					let locals = name_section.locals_mut().get_or_insert_with(Default::default);
+					for (body_index, names) in module.local_names {
+						let mut name_map = elements::NameMap::default();
+						for (local_index, name) in names.into_iter().enumerate() {
+							name_map.insert(local_index as u32, name);
+						}
+						locals.local_names_mut().insert(imported_functions + body_index, name_map);
+					}
+					result
+				},
+				Err((_, result)) => result,
+			};
+		}
+		result
 	}
 }
 
@@ -224,6 +262,9 @@ where
 		let signature_index = self.module.functions.entries_mut().len() as u32 - 1;
 		self.module.code.bodies_mut().push(body);
 		let body_index = self.module.code.bodies_mut().len() as u32 - 1;
+		if !func.local_names.is_empty() {
+			self.module.local_names.push((body_index, func.local_names));
+		}
 
 		if func.is_main {
 			self.module.start = Some(body_index);
@@ -259,6 +300,12 @@ where
 				entry.values,
 			))
 		}
+		#[cfg(feature = "bulk")]
+		for values in table.passive_elements.drain(..) {
+			let mut segment = elements::ElementSegment::new(0, None, values);
+			segment.set_passive(true);
+			self.module.element.entries_mut().push(segment);
+		}
 		table_index
 	}
 
@@ -292,6 +339,13 @@ where
 		self.resolve_type_ref(signature)
 	}
 
+	/// Push a signature to be used as a block type, returning the block type referencing it.
+	/// Can create corresponding type in type section.
+	#[cfg(feature = "multi_value")]
+	pub fn push_block_type(&mut self, signature: code::Signature) -> elements::BlockType {
+		elements::BlockType::TypeIndex(self.resolve_type_ref(signature))
+	}
+
 	/// Push signatures in the module, returning corresponding indices of pushed signatures
 	pub fn push_signatures(&mut self, signatures: code::SignatureBindings) -> Vec<u32> {
 		signatures.into_iter().map(|binding| self.resolve_type_ref(binding)).collect()
@@ -376,6 +430,12 @@ where
 		self
 	}
 
+	/// With start function, given by its index in the function space
+	pub fn with_start(mut self, func_index: u32) -> Self {
+		self.module.start = Some(func_index);
+		self
+	}
+
 	/// Export entry builder
 	/// # Examples
 	/// ```
@@ -632,6 +692,41 @@ mod tests {
 		assert_eq!(module.data_section().expect("data section to exist").entries().len(), 1);
 	}
 
+	#[test]
+	fn data_roundtrip() {
+		let module = module()
+			.data()
+			.offset(elements::Instruction::I32Const(16))
+			.value(vec![0u8, 15, 10, 5, 25])
+			.build()
+			.build();
+
+		let module = elements::Module::from_bytes(module.into_bytes().expect("serialization"))
+			.expect("deserialization");
+		let segment = &module.data_section().expect("data section to exist").entries()[0];
+		assert_eq!(
+			segment.offset(),
+			&Some(elements::InitExpr::new(vec![
+				elements::Instruction::I32Const(16),
+				elements::Instruction::End,
+			]))
+		);
+		assert_eq!(segment.value(), &[0u8, 15, 10, 5, 25]);
+	}
+
+	#[cfg(feature = "bulk")]
+	#[test]
+	fn passive_data_roundtrip() {
+		let module = module().data().passive().value(vec![0u8, 15, 10]).build().build();
+
+		let module = elements::Module::from_bytes(module.into_bytes().expect("serialization"))
+			.expect("deserialization");
+		let segment = &module.data_section().expect("data section to exist").entries()[0];
+		assert!(segment.passive());
+		assert_eq!(segment.offset(), &None);
+		assert_eq!(segment.value(), &[0u8, 15, 10]);
+	}
+
 	#[test]
 	fn reuse_types() {
 		let module = module()
@@ -655,4 +750,195 @@ mod tests {
 
 		assert_eq!(module.type_section().expect("type section failed").types().len(), 1);
 	}
+
+	#[cfg(feature = "multi_value")]
+	#[test]
+	fn multi_value_block() {
+		use super::{super::signature, ModuleBuilder};
+		use elements::{Instruction, Instructions, ValueType};
+
+		let mut builder = ModuleBuilder::new();
+		let block_type = builder.push_block_type(
+			signature()
+				.with_param(ValueType::I32)
+				.with_results([ValueType::I32, ValueType::I64])
+				.build_sig(),
+		);
+		let module = builder
+			.function()
+			.signature()
+			.with_param(ValueType::I32)
+			.build()
+			.body()
+			.with_instructions(Instructions::new(vec![
+				Instruction::GetLocal(0),
+				Instruction::Block(block_type),
+				Instruction::I64Const(1),
+				Instruction::End,
+				Instruction::Drop,
+				Instruction::Drop,
+				Instruction::End,
+			]))
+			.build()
+			.build()
+			.build();
+
+		assert_eq!(module.type_section().expect("type section to exist").types().len(), 2);
+		let module = elements::Module::from_bytes(module.into_bytes().expect("serialization"))
+			.expect("deserialization");
+		let body = &module.code_section().expect("code section to exist").bodies()[0];
+		assert_eq!(
+			body.code().elements()[1],
+			Instruction::Block(elements::BlockType::TypeIndex(0))
+		);
+	}
+
+	#[cfg(feature = "multi_value")]
+	#[test]
+	fn multi_value_block_unknown_type() {
+		use elements::{BlockType, Instruction, Instructions};
+
+		// Type indices of blocks are not checked by the builder, that is left to validation.
+		let module = module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.with_instructions(Instructions::new(vec![
+				Instruction::Block(BlockType::TypeIndex(3)),
+				Instruction::End,
+				Instruction::End,
+			]))
+			.build()
+			.build()
+			.build();
+
+		let body = &module.code_section().expect("code section to exist").bodies()[0];
+		assert_eq!(body.code().elements()[0], Instruction::Block(BlockType::TypeIndex(3)));
+	}
+
+	#[cfg(feature = "bulk")]
+	#[test]
+	fn passive_element_segment() {
+		let module = module().table().with_min(1).with_passive_element(vec![0, 1]).build().build();
+
+		let module = elements::Module::from_bytes(module.into_bytes().expect("serialization"))
+			.expect("deserialization");
+		let segment = &module.elements_section().expect("element section to exist").entries()[0];
+		assert!(segment.passive());
+		assert_eq!(segment.offset(), &None);
+		assert_eq!(segment.members(), &[0, 1]);
+	}
+
+	#[test]
+	fn local_names_roundtrip() {
+		let module = module()
+			.import()
+			.module("env")
+			.field("log")
+			.external()
+			.func(0)
+			.build()
+			.function()
+			.signature()
+			.with_param(elements::ValueType::I32)
+			.build()
+			.body()
+			.with_locals(vec![elements::Local::new(1, elements::ValueType::I64)])
+			.build()
+			.with_local_names(&["arg", "tmp"])
+			.build()
+			.build();
+
+		let module = elements::Module::from_bytes(module.into_bytes().expect("serialization"))
+			.expect("deserialization")
+			.parse_names()
+			.expect("names to parse");
+		let names = module.names_section().expect("name section to exist");
+		let locals = names.locals().expect("local names to exist").local_names();
+		assert!(locals.get(0).is_none());
+		let func_locals = locals.get(1).expect("local names of the function to exist");
+		assert_eq!(func_locals.get(0).map(String::as_str), Some("arg"));
+		assert_eq!(func_locals.get(1).map(String::as_str), Some("tmp"));
+	}
+
+	#[test]
+	fn local_names_merge_raw_name_section() {
+		use super::from_module;
+
+		let original =
+			elements::deserialize_file("./res/cases/v1/with_names.wasm").expect("should load");
+		let function_names = original
+			.clone()
+			.parse_names()
+			.expect("names to parse")
+			.names_section()
+			.and_then(|names| names.functions())
+			.map(|functions| functions.names().len())
+			.expect("function names to exist");
+
+		let module = from_module(original)
+			.function()
+			.signature()
+			.build()
+			.body()
+			.with_locals(vec![elements::Local::new(1, elements::ValueType::I32)])
+			.build()
+			.with_local_names(&["tmp"])
+			.build()
+			.build();
+
+		let module = elements::Module::from_bytes(module.into_bytes().expect("serialization"))
+			.expect("deserialization");
+		let raw_names = module.custom_sections().filter(|custom| custom.name() == "name").count();
+		assert_eq!(raw_names, 1);
+
+		let module = module.parse_names().expect("names to parse");
+		let names = module.names_section().expect("name section to exist");
+		assert_eq!(names.functions().expect("function names").names().len(), function_names);
+		assert!(names.locals().is_some());
+	}
+
+	#[test]
+	fn local_names_keep_invalid_name_section() {
+		use super::from_module;
+
+		let mut original = elements::Module::default();
+		original.set_custom_section("name", vec![0x01, 0x02, 0x05, 0x00]);
+		let module = from_module(original)
+			.function()
+			.signature()
+			.param()
+			.i32()
+			.build()
+			.body()
+			.build()
+			.with_local_names(&["arg"])
+			.build()
+			.build();
+
+		let custom = module.custom_sections().find(|custom| custom.name() == "name");
+		assert_eq!(custom.map(|custom| custom.payload()), Some(&[0x01, 0x02, 0x05, 0x00][..]));
+		assert!(module.names_section().is_none());
+	}
+
+	#[test]
+	fn with_start() {
+		let module = module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.build()
+			.build()
+			.with_start(0)
+			.build();
+		assert_eq!(module.start_section(), Some(0));
+
+		let mut module = module;
+		module.clear_start_section();
+		assert_eq!(module.start_section(), None);
+		module.set_start_section(0);
+		assert_eq!(module.start_section(), Some(0));
+	}
 }
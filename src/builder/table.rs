@@ -11,6 +11,10 @@ pub struct TableDefinition {
 	pub max: Option<u32>,
 	/// Element segments, if any
 	pub elements: Vec<TableEntryDefinition>,
+
+	/// Values of passive element segments, set with `TableBuilder::with_passive_element`
+	#[cfg(feature = "bulk")]
+	pub(crate) passive_elements: Vec<Vec<u32>>,
 }
 
 /// Table elements entry definition
@@ -74,6 +78,13 @@ where
 		self
 	}
 
+	/// Generate passive element segment (without table index and offset) with the specified values
+	#[cfg(feature = "bulk")]
+	pub fn with_passive_element(mut self, values: Vec<u32>) -> Self {
+		self.table.passive_elements.push(values);
+		self
+	}
+
 	/// Finalize current builder spawning resulting struct
 	pub fn build(self) -> F::Result {
 		self.callback.invoke(self.table)
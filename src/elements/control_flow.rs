@@ -0,0 +1,235 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use super::{FuncBody, Instruction};
+
+/// Straight-line run of instructions of a function body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+	range: Range<usize>,
+	successors: Vec<usize>,
+}
+
+impl BasicBlock {
+	/// Indices of the instructions of this block in the function body.
+	pub fn range(&self) -> Range<usize> {
+		self.range.clone()
+	}
+
+	/// Indices of the blocks control can pass to after this one.
+	///
+	/// Empty for the block ending the function and for blocks ending with `unreachable`.
+	pub fn successors(&self) -> &[usize] {
+		&self.successors
+	}
+}
+
+/// Control flow graph of a function body.
+///
+/// A branch to a `block` or `if` goes to its `end`, a branch to a `loop` goes to the
+/// instruction following `loop`. Branches to the function label and `return` go to the
+/// final `end` of the body.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ControlFlowGraph {
+	blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+	/// Basic blocks, ordered by position in the function body.
+	pub fn blocks(&self) -> &[BasicBlock] {
+		&self.blocks
+	}
+
+	/// Index of the block containing the instruction at `instruction_index`.
+	pub fn block_at(&self, instruction_index: usize) -> Option<usize> {
+		match self.blocks.binary_search_by_key(&instruction_index, |block| block.range.start) {
+			Ok(index) => Some(index),
+			Err(0) => None,
+			Err(index) if instruction_index < self.blocks[index - 1].range.end => Some(index - 1),
+			Err(_) => None,
+		}
+	}
+}
+
+/// Matching `else` and `end` of a block-opening instruction.
+#[derive(Clone, Copy, Default)]
+struct Frame {
+	else_index: Option<usize>,
+	end_index: usize,
+}
+
+impl FuncBody {
+	/// Build the control flow graph of this function body.
+	///
+	/// Bodies with unbalanced blocks are handled leniently: unclosed blocks are treated
+	/// as closed by the last instruction, and out-of-range labels as branches to the end
+	/// of the function.
+	pub fn control_flow_graph(&self) -> ControlFlowGraph {
+		let code = self.code().elements();
+		let exit = match code.len() {
+			0 => return ControlFlowGraph::default(),
+			len => len - 1,
+		};
+
+		// Find `else` and `end` of every block.
+		let mut frames = vec![Frame::default(); code.len()];
+		let mut open = Vec::new();
+		for (index, instruction) in code.iter().enumerate() {
+			match *instruction {
+				ref instruction if instruction.is_block() => open.push(index),
+				Instruction::Else =>
+					if let Some(&opener) = open.last() {
+						frames[opener].else_index = Some(index);
+					},
+				ref instruction if instruction.is_terminal() =>
+					if let Some(opener) = open.pop() {
+						frames[opener].end_index = index;
+					},
+				_ => {},
+			}
+		}
+		for opener in open {
+			frames[opener].end_index = exit;
+		}
+
+		// Successors (as instruction indices) of the instruction at every block boundary.
+		let label = |open: &[usize], depth: u32| {
+			let opener = match open.len().checked_sub(depth as usize + 1) {
+				Some(position) => open[position],
+				None => return exit,
+			};
+			match code[opener] {
+				Instruction::Loop(_) => opener + 1,
+				_ => frames[opener].end_index,
+			}
+		};
+		let mut open = Vec::new();
+		let mut leaders = vec![0];
+		let mut exits: Vec<(usize, Vec<usize>)> = Vec::new();
+		for (index, instruction) in code.iter().enumerate() {
+			let targets = match *instruction {
+				Instruction::Br(depth) => vec![label(&open, depth)],
+				Instruction::BrIf(depth) => vec![label(&open, depth), index + 1],
+				Instruction::BrTable(ref table) => {
+					let mut targets: Vec<usize> =
+						table.table.iter().map(|&depth| label(&open, depth)).collect();
+					targets.push(label(&open, table.default));
+					targets.sort_unstable();
+					targets.dedup();
+					targets
+				},
+				Instruction::Return => vec![exit],
+				Instruction::Unreachable => vec![],
+				Instruction::If(_) => {
+					let frame = frames[index];
+					let otherwise = frame.else_index.map_or(frame.end_index, |i| i + 1);
+					vec![index + 1, otherwise]
+				},
+				Instruction::Else => match open.last() {
+					Some(&opener) => vec![frames[opener].end_index],
+					None => vec![exit],
+				},
+				_ => {
+					if instruction.is_block() {
+						open.push(index);
+					} else if instruction.is_terminal() {
+						open.pop();
+					}
+					continue
+				},
+			};
+			if let Instruction::If(_) = *instruction {
+				open.push(index);
+			}
+			leaders.extend(targets.iter().copied().filter(|&target| target <= exit));
+			if index < exit {
+				leaders.push(index + 1);
+			}
+			exits.push((index, targets));
+		}
+		leaders.sort_unstable();
+		leaders.dedup();
+
+		let block_of = |instruction_index: usize| {
+			leaders.binary_search(&instruction_index).unwrap_or_else(|index| index - 1)
+		};
+		let mut blocks: Vec<BasicBlock> = leaders
+			.iter()
+			.enumerate()
+			.map(|(index, &start)| {
+				let end = leaders.get(index + 1).copied().unwrap_or(code.len());
+				let successors = if end <= exit { vec![index + 1] } else { vec![] };
+				BasicBlock { range: start..end, successors }
+			})
+			.collect();
+		for (index, targets) in exits {
+			let block = &mut blocks[block_of(index)];
+			block.successors = targets.into_iter().map(block_of).collect();
+			block.successors.dedup();
+		}
+
+		ControlFlowGraph { blocks }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::{BlockType, FuncBody, Instruction::*, Instructions};
+
+	#[test]
+	fn if_else() {
+		let body = FuncBody::new(
+			vec![],
+			Instructions::new(vec![
+				GetLocal(0),
+				If(BlockType::NoResult),
+				I32Const(1),
+				Drop,
+				Else,
+				I32Const(2),
+				Drop,
+				End,
+				End,
+			]),
+		);
+
+		let graph = body.control_flow_graph();
+		let blocks = graph.blocks();
+		assert_eq!(blocks.len(), 4);
+		assert_eq!((blocks[0].range(), blocks[0].successors()), (0..2, &[1, 2][..]));
+		assert_eq!((blocks[1].range(), blocks[1].successors()), (2..5, &[3][..]));
+		assert_eq!((blocks[2].range(), blocks[2].successors()), (5..7, &[3][..]));
+		assert_eq!((blocks[3].range(), blocks[3].successors()), (7..9, &[][..]));
+		assert_eq!(graph.block_at(6), Some(2));
+		assert_eq!(graph.block_at(9), None);
+	}
+
+	#[test]
+	fn loop_branches() {
+		let body = FuncBody::new(
+			vec![],
+			Instructions::new(vec![
+				Block(BlockType::NoResult),
+				Loop(BlockType::NoResult),
+				GetLocal(0),
+				BrIf(1),
+				Br(0),
+				End,
+				End,
+				Return,
+				End,
+			]),
+		);
+
+		let graph = body.control_flow_graph();
+		let blocks = graph.blocks();
+		assert_eq!(blocks.len(), 6);
+		assert_eq!((blocks[0].range(), blocks[0].successors()), (0..2, &[1][..]));
+		assert_eq!((blocks[1].range(), blocks[1].successors()), (2..4, &[4, 2][..]));
+		assert_eq!((blocks[2].range(), blocks[2].successors()), (4..5, &[1][..]));
+		// `end` of the loop follows an unconditional `br`, so it is dead code
+		assert_eq!((blocks[3].range(), blocks[3].successors()), (5..6, &[4][..]));
+		assert_eq!((blocks[4].range(), blocks[4].successors()), (6..8, &[5][..]));
+		assert_eq!((blocks[5].range(), blocks[5].successors()), (8..9, &[][..]));
+	}
+}
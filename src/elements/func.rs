@@ -1,9 +1,13 @@
 use super::{
-	CountedList, CountedListWriter, CountedWriter, Deserialize, Error, Instructions, Serialize,
-	ValueType, VarUint32,
+	CountedList, CountedListWriter, CountedWriter, Deserialize, Error, Instruction, Instructions,
+	ParseConfig, Serialize, ValueType, VarUint32,
 };
 use crate::{elements::section::SectionReader, io};
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
+use core::{cmp, fmt::Write};
+
+#[cfg(feature = "exceptions")]
+use super::ExceptionInstruction;
 
 /// Function signature (type reference)
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -125,44 +129,228 @@ impl FuncBody {
 	pub fn code_mut(&mut self) -> &mut Instructions {
 		&mut self.instructions
 	}
+
+	/// Merge adjacent local declarations of the same type into a single run.
+	pub fn normalize_locals(&mut self) {
+		let mut merged: Vec<Local> = Vec::with_capacity(self.locals.len());
+		for local in self.locals.drain(..) {
+			match merged.last_mut() {
+				Some(last) if last.value_type == local.value_type => last.count += local.count,
+				_ => merged.push(local),
+			}
+		}
+		self.locals = merged;
+	}
+
+	/// Declare one more local of type `value_type`, extending the last run if it has the
+	/// same type.
+	///
+	/// Returns the index of the new local among the declared locals; function parameters
+	/// come first in the local index space, so the actual local index is offset by the
+	/// number of parameters of the function signature.
+	pub fn add_local(&mut self, value_type: ValueType) -> u32 {
+		let index = self.locals.iter().map(|local| local.count).sum();
+		match self.locals.last_mut() {
+			Some(last) if last.value_type == value_type => last.count += 1,
+			_ => self.locals.push(Local::new(1, value_type)),
+		}
+		index
+	}
+
+	/// Maximum nesting depth of blocks, loops and ifs in the body.
+	///
+	/// The function body itself is not counted, so a body without blocks has depth `0`.
+	pub fn max_block_depth(&self) -> usize {
+		let mut depth = 0usize;
+		let mut max_depth = 0;
+		for instruction in self.instructions.elements() {
+			if instruction.is_block() {
+				depth += 1;
+				max_depth = cmp::max(max_depth, depth);
+			} else if instruction.is_terminal() {
+				depth = depth.saturating_sub(1);
+			}
+		}
+		max_depth
+	}
+
+	/// Text listing of the body, one instruction per line, indented by block nesting.
+	///
+	/// Locals are listed first, one line per declaration.
+	pub fn to_text(&self) -> String {
+		let mut text = String::new();
+		for local in &self.locals {
+			let _ = writeln!(text, "local {} {}", local.count(), local.value_type());
+		}
+
+		let mut depth = 0usize;
+		for instruction in self.instructions.elements() {
+			let continues_block = is_block_continuation(instruction);
+			if instruction.is_terminal() || continues_block {
+				depth = depth.saturating_sub(1);
+			}
+			for _ in 0..depth {
+				text.push_str("  ");
+			}
+			let _ = writeln!(text, "{}", instruction);
+			if instruction.is_block() || continues_block {
+				depth += 1;
+			}
+		}
+		text
+	}
+}
+
+/// Is this instruction ending one arm of a block and starting the next (like `else`)?
+fn is_block_continuation(instruction: &Instruction) -> bool {
+	match *instruction {
+		Instruction::Else => true,
+		#[cfg(feature = "exceptions")]
+		Instruction::Exception(ExceptionInstruction::Catch(_)) |
+		Instruction::Exception(ExceptionInstruction::CatchAll) => true,
+		_ => false,
+	}
 }
 
 impl Deserialize for FuncBody {
 	type Error = Error;
 
 	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
-		let mut body_reader = SectionReader::new(reader)?;
-		let locals: Vec<Local> = CountedList::<Local>::deserialize(&mut body_reader)?.into_inner();
-
-		// The specification obliges us to count the total number of local variables while
-		// decoding the binary format.
-		locals
-			.iter()
-			.try_fold(0u32, |acc, &Local { count, .. }| acc.checked_add(count))
-			.ok_or(Error::TooManyLocals)?;
+		FuncBody::deserialize_with(reader, ParseConfig::default())
+	}
+}
 
-		let instructions = Instructions::deserialize(&mut body_reader)?;
-		body_reader.close()?;
-		Ok(FuncBody { locals, instructions })
+impl FuncBody {
+	pub(crate) fn deserialize_with<R: io::Read>(
+		reader: &mut R,
+		config: ParseConfig,
+	) -> Result<Self, Error> {
+		SectionReader::parse(reader, |body_reader| {
+			let locals: Vec<Local> = CountedList::<Local>::deserialize(body_reader)?.into_inner();
+
+			// The specification obliges us to count the total number of local variables while
+			// decoding the binary format.
+			locals
+				.iter()
+				.try_fold(0u32, |acc, &Local { count, .. }| acc.checked_add(count))
+				.ok_or(Error::TooManyLocals)?;
+
+			let instructions = Instructions::deserialize_with(body_reader, config)?;
+			Ok(FuncBody { locals, instructions })
+		})
 	}
 }
 
 impl Serialize for FuncBody {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &FuncBody {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
 
-		let data = self.locals;
-		let counted_list =
-			CountedListWriter::<Local, _>(data.len(), data.into_iter().map(Into::into));
+		let data = &self.locals;
+		let counted_list = CountedListWriter::<Local, _>(data.len(), data.iter().copied());
 		counted_list.serialize(&mut counted_writer)?;
 
-		let code = self.instructions;
-		code.serialize(&mut counted_writer)?;
+		(&self.instructions).serialize(&mut counted_writer)?;
 
 		counted_writer.done()?;
 
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		super::{deserialize_buffer, Instructions, ValueType},
+		FuncBody, Local,
+	};
+	use crate::io;
+
+	#[test]
+	fn to_text() {
+		// see if-else.wast/if-else.wasm
+		let instructions = deserialize_buffer::<Instructions>(&[
+			0x04, 0x7F, 0x41, 0x05, 0x05, 0x41, 0x07, 0x0B, 0x0B,
+		])
+		.expect("valid hex of if instruction");
+		let body = FuncBody::new(vec![Local::new(2, ValueType::I64)], instructions);
+
+		assert_eq!(
+			body.to_text(),
+			"local 2 i64\nif i32\n  i32.const 5\nelse\n  i32.const 7\nend\nend\n"
+		);
+	}
+
+	#[test]
+	fn standalone_roundtrip() {
+		use super::super::{deserialize_file, serialize, Deserialize, VarUint32, VarUint7};
+
+		let module = deserialize_file("./res/cases/v1/test5.wasm").expect("Should be deserialized");
+		let bytes = serialize(module.clone()).expect("Should be serialized");
+
+		// Walk the sections up to the first body of the code section.
+		let mut reader = io::Cursor::new(&bytes[8..]);
+		loop {
+			let id: u8 = VarUint7::deserialize(&mut reader).expect("section id").into();
+			let len: u32 = VarUint32::deserialize(&mut reader).expect("section length").into();
+			if id == 10 {
+				VarUint32::deserialize(&mut reader).expect("body count");
+				break
+			}
+			let mut payload = vec![0; len as usize];
+			io::Read::read(&mut reader, &mut payload).expect("section payload");
+		}
+		let start = 8 + reader.position();
+		let size: u32 = VarUint32::deserialize(&mut reader).expect("body size").into();
+		let end = 8 + reader.position() + size as usize;
+
+		let body: FuncBody =
+			deserialize_buffer(&bytes[start..end]).expect("Should be deserialized");
+		assert_eq!(&body, &module.code_section().expect("Should have code section").bodies()[0]);
+		assert_eq!(serialize(body).expect("Should be serialized"), &bytes[start..end]);
+	}
+
+	#[test]
+	fn add_local() {
+		let params = 2;
+		let mut body = FuncBody::empty();
+		let first = params + body.add_local(ValueType::I32);
+		let second = params + body.add_local(ValueType::I32);
+		let third = params + body.add_local(ValueType::F64);
+
+		assert_eq!((first, second, third), (2, 3, 4));
+		assert_eq!(body.locals(), &[Local::new(2, ValueType::I32), Local::new(1, ValueType::F64)]);
+	}
+
+	#[test]
+	fn normalize_locals() {
+		let mut body = FuncBody::new(
+			vec![
+				Local::new(1, ValueType::I32),
+				Local::new(2, ValueType::I32),
+				Local::new(1, ValueType::I64),
+				Local::new(1, ValueType::I32),
+			],
+			Instructions::empty(),
+		);
+		body.normalize_locals();
+
+		assert_eq!(
+			body.locals(),
+			&[
+				Local::new(3, ValueType::I32),
+				Local::new(1, ValueType::I64),
+				Local::new(1, ValueType::I32),
+			]
+		);
+	}
+}
@@ -45,8 +45,16 @@ impl Deserialize for GlobalEntry {
 impl Serialize for GlobalEntry {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &GlobalEntry {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		self.global_type.serialize(writer)?;
-		self.init_expr.serialize(writer)
+		(&self.init_expr).serialize(writer)
 	}
 }
@@ -4,6 +4,7 @@ use super::{
 };
 use crate::io;
 use alloc::string::String;
+use core::cmp;
 
 const FLAG_HAS_MAX: u8 = 0x01;
 #[cfg(feature = "atomics")]
@@ -63,7 +64,21 @@ pub struct TableType {
 impl TableType {
 	/// New table definition
 	pub fn new(min: u32, max: Option<u32>) -> Self {
-		TableType { elem_type: TableElementType::AnyFunc, limits: ResizableLimits::new(min, max) }
+		TableType::with_elem_type(TableElementType::AnyFunc, min, max)
+	}
+
+	/// New table definition with the given element type.
+	///
+	/// # Examples
+	/// ```
+	/// use parity_wasm::elements::{TableElementType, TableType};
+	///
+	/// let table = TableType::with_elem_type(TableElementType::AnyFunc, 64, None);
+	/// assert_eq!(table, TableType::new(64, None));
+	/// assert_eq!(table.limits().initial(), 64);
+	/// ```
+	pub fn with_elem_type(elem_type: TableElementType, min: u32, max: Option<u32>) -> Self {
+		TableType { elem_type, limits: ResizableLimits::new(min, max) }
 	}
 
 	/// Table memory specification
@@ -124,6 +139,34 @@ impl ResizableLimits {
 		self.maximum
 	}
 
+	/// Check that the maximum size, if any, is not less than the initial size.
+	pub fn validate(&self) -> Result<(), Error> {
+		match self.maximum {
+			Some(maximum) if maximum < self.initial =>
+				Err(Error::InvalidLimits { initial: self.initial, maximum }),
+			_ => Ok(()),
+		}
+	}
+
+	/// Set the maximum size.
+	///
+	/// Fails, leaving the limits unchanged, if the new maximum is less than the initial size.
+	pub fn set_maximum(&mut self, maximum: Option<u32>) -> Result<(), Error> {
+		ResizableLimits { maximum, ..*self }.validate()?;
+		self.maximum = maximum;
+		Ok(())
+	}
+
+	/// Lower the maximum size to `cap`, if it is greater.
+	///
+	/// Limits without a maximum are left unbounded, and the maximum is never lowered below
+	/// the initial size.
+	pub fn clamp_maximum(&mut self, cap: u32) {
+		if let Some(ref mut maximum) = self.maximum {
+			*maximum = cmp::min(*maximum, cmp::max(cap, self.initial));
+		}
+	}
+
 	#[cfg(feature = "atomics")]
 	/// Whether or not this is a shared array buffer.
 	pub fn shared(&self) -> bool {
@@ -177,9 +220,12 @@ impl Serialize for ResizableLimits {
 		#[cfg(feature = "atomics")]
 		{
 			// If the atomics feature is enabled and if the shared flag is set, add logically
-			// it to the flags.
+			// it to the flags. Shared limits must have a maximum.
 			if self.shared {
 				flags |= FLAG_SHARED;
+				if self.maximum.is_none() {
+					return Err(Error::InvalidLimitsFlags(flags))
+				}
 			}
 		}
 		Uint8::from(flags).serialize(writer)?;
@@ -195,13 +241,48 @@ impl Serialize for ResizableLimits {
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct MemoryType(ResizableLimits);
 
+impl From<ResizableLimits> for MemoryType {
+	fn from(limits: ResizableLimits) -> Self {
+		MemoryType(limits)
+	}
+}
+
 impl MemoryType {
 	/// New memory definition
+	///
+	/// # Examples
+	/// ```
+	/// use parity_wasm::elements::MemoryType;
+	///
+	/// let memory = MemoryType::new(256, None);
+	/// assert_eq!(memory.limits().initial(), 256);
+	/// assert_eq!(memory.limits().maximum(), None);
+	/// ```
 	pub fn new(min: u32, max: Option<u32>) -> Self {
 		let r = ResizableLimits::new(min, max);
 		MemoryType(r)
 	}
 
+	/// New shared memory definition.
+	///
+	/// Shared memories must declare a maximum. This is only available if the `atomics`
+	/// feature is enabled.
+	///
+	/// # Examples
+	/// ```
+	/// use parity_wasm::elements::MemoryType;
+	///
+	/// let memory = MemoryType::new_shared(1, 10);
+	/// assert!(memory.limits().shared());
+	/// assert_eq!(memory.limits().maximum(), Some(10));
+	/// ```
+	#[cfg(feature = "atomics")]
+	pub fn new_shared(min: u32, max: u32) -> Self {
+		let mut memory = MemoryType::new(min, Some(max));
+		memory.set_shared(true);
+		memory
+	}
+
 	/// Set the `shared` flag that denotes a memory that can be shared between threads.
 	///
 	/// `false` by default. This is only available if the `atomics` feature is enabled.
@@ -232,6 +313,61 @@ impl Serialize for MemoryType {
 	}
 }
 
+/// Tag entry, describing the signature of an exception.
+///
+/// This is only available if the `exceptions` feature is enabled.
+#[cfg(feature = "exceptions")]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TagType {
+	attribute: u8,
+	type_ref: u32,
+}
+
+#[cfg(feature = "exceptions")]
+impl TagType {
+	/// New exception tag with the signature at the given index of the type section.
+	pub fn new(type_ref: u32) -> Self {
+		TagType { attribute: 0, type_ref }
+	}
+
+	/// Tag attribute, `0` denotes an exception.
+	pub fn attribute(&self) -> u8 {
+		self.attribute
+	}
+
+	/// Index of the function signature of the tag.
+	pub fn type_ref(&self) -> u32 {
+		self.type_ref
+	}
+
+	/// Index of the function signature of the tag (mutable).
+	pub fn type_ref_mut(&mut self) -> &mut u32 {
+		&mut self.type_ref
+	}
+}
+
+#[cfg(feature = "exceptions")]
+impl Deserialize for TagType {
+	type Error = Error;
+
+	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
+		let attribute = Uint8::deserialize(reader)?.into();
+		let type_ref = VarUint32::deserialize(reader)?.into();
+		Ok(TagType { attribute, type_ref })
+	}
+}
+
+#[cfg(feature = "exceptions")]
+impl Serialize for TagType {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		Uint8::from(self.attribute).serialize(writer)?;
+		VarUint32::from(self.type_ref).serialize(writer)?;
+		Ok(())
+	}
+}
+
 /// External to local binding.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum External {
@@ -244,6 +380,9 @@ pub enum External {
 	Memory(MemoryType),
 	/// Describes local global entry to be imported as.
 	Global(GlobalType),
+	/// Describes exception tag to be imported as.
+	#[cfg(feature = "exceptions")]
+	Tag(TagType),
 }
 
 impl Deserialize for External {
@@ -256,6 +395,8 @@ impl Deserialize for External {
 			0x01 => Ok(External::Table(TableType::deserialize(reader)?)),
 			0x02 => Ok(External::Memory(MemoryType::deserialize(reader)?)),
 			0x03 => Ok(External::Global(GlobalType::deserialize(reader)?)),
+			#[cfg(feature = "exceptions")]
+			0x04 => Ok(External::Tag(TagType::deserialize(reader)?)),
 			_ => Err(Error::UnknownExternalKind(kind.into())),
 		}
 	}
@@ -284,6 +425,11 @@ impl Serialize for External {
 				VarInt7::from(0x03).serialize(writer)?;
 				gt.serialize(writer)?;
 			},
+			#[cfg(feature = "exceptions")]
+			Tag(tt) => {
+				VarInt7::from(0x04).serialize(writer)?;
+				tt.serialize(writer)?;
+			},
 		}
 
 		Ok(())
@@ -351,8 +497,16 @@ impl Serialize for ImportEntry {
 	type Error = Error;
 
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
-		self.module_str.serialize(writer)?;
-		self.field_str.serialize(writer)?;
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &ImportEntry {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		self.module_str.as_str().serialize(writer)?;
+		self.field_str.as_str().serialize(writer)?;
 		self.external.serialize(writer)
 	}
 }
@@ -154,18 +154,19 @@ impl<T> IndexMap<T> {
 		F: Fn(u32, &mut R) -> Result<T, Error>,
 	{
 		let len: u32 = VarUint32::deserialize(rdr)?.into();
-		let mut map = IndexMap::with_capacity(len as usize);
+		// `len` is untrusted, but there can't be more entries than indices allowed
+		let mut map = IndexMap::with_capacity(min(len as usize, max_entry_space));
 		let mut prev_idx = None;
 		for _ in 0..len {
 			let idx: u32 = VarUint32::deserialize(rdr)?.into();
 			if idx as usize >= max_entry_space {
-				return Err(Error::Other("index is larger than expected"))
+				return Err(Error::NameIndexOutOfBounds { index: idx, len: max_entry_space as u32 })
 			}
 			match prev_idx {
 				Some(prev) if prev >= idx => {
 					// Supposedly these names must be "sorted by index", so
 					// let's try enforcing that and seeing what happens.
-					return Err(Error::Other("indices are out of order"))
+					return Err(Error::NameIndicesOutOfOrder(idx))
 				},
 				_ => {
 					prev_idx = Some(idx);
@@ -555,7 +556,7 @@ mod tests {
 		VarUint32::from(0u32).serialize(&mut invalid).unwrap();
 		"val 0".to_string().serialize(&mut invalid).unwrap();
 		let res = IndexMap::<String>::deserialize(2, &mut io::Cursor::new(invalid));
-		assert!(res.is_err());
+		assert!(matches!(res, Err(Error::NameIndicesOutOfOrder(0))));
 	}
 
 	#[test]
@@ -566,6 +567,6 @@ mod tests {
 		VarUint32::from(5u32).serialize(&mut invalid).unwrap();
 		"val 5".to_string().serialize(&mut invalid).unwrap();
 		let res = IndexMap::<String>::deserialize(1, &mut io::Cursor::new(invalid));
-		assert!(res.is_err());
+		assert!(matches!(res, Err(Error::NameIndexOutOfBounds { index: 5, len: 1 })));
 	}
 }
@@ -0,0 +1,138 @@
+use alloc::{format, string::String};
+
+use super::{External, Internal, Module, Type, ValueType};
+
+impl Module {
+	/// Minimal JSON dump of the module structure.
+	///
+	/// Lists sections (as in `Display`), signatures, imports, exports and the
+	/// instruction mnemonics of each function body.
+	pub fn to_json(&self) -> String {
+		let mut json = String::from("{\"sections\":[");
+		let sections = format!("{}", self);
+		write_list(&mut json, sections.lines(), write_string);
+
+		json.push_str("],\"types\":[");
+		let types = self.type_section().map(|ts| ts.types()).unwrap_or(&[]);
+		write_list(&mut json, types, |json, Type::Function(func_type)| {
+			json.push_str("{\"params\":[");
+			write_list(json, func_type.params(), write_value_type);
+			json.push_str("],\"results\":[");
+			write_list(json, func_type.results(), write_value_type);
+			json.push_str("]}");
+		});
+
+		json.push_str("],\"imports\":[");
+		let imports = self.import_section().map(|is| is.entries()).unwrap_or(&[]);
+		write_list(&mut json, imports, |json, entry| {
+			json.push_str("{\"module\":");
+			write_string(json, entry.module());
+			json.push_str(",\"field\":");
+			write_string(json, entry.field());
+			json.push_str(",\"kind\":");
+			write_string(json, external_kind(entry.external()));
+			json.push('}');
+		});
+
+		json.push_str("],\"exports\":[");
+		let exports = self.export_section().map(|es| es.entries()).unwrap_or(&[]);
+		write_list(&mut json, exports, |json, entry| {
+			let (kind, index) = internal_kind(entry.internal());
+			json.push_str("{\"field\":");
+			write_string(json, entry.field());
+			json.push_str(",\"kind\":");
+			write_string(json, kind);
+			json.push_str(&format!(",\"index\":{}}}", index));
+		});
+
+		json.push_str("],\"functions\":[");
+		let bodies = self.code_section().map(|cs| cs.bodies()).unwrap_or(&[]);
+		write_list(&mut json, bodies, |json, body| {
+			json.push('[');
+			write_list(json, body.code().elements(), |json, instruction| {
+				let text = format!("{}", instruction);
+				write_string(json, text.split_whitespace().next().unwrap_or(""));
+			});
+			json.push(']');
+		});
+
+		json.push_str("]}");
+		json
+	}
+}
+
+fn write_list<I, F>(json: &mut String, items: I, mut write_item: F)
+where
+	I: IntoIterator,
+	F: FnMut(&mut String, I::Item),
+{
+	for (i, item) in items.into_iter().enumerate() {
+		if i > 0 {
+			json.push(',');
+		}
+		write_item(json, item);
+	}
+}
+
+fn write_string(json: &mut String, s: &str) {
+	json.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => json.push_str("\\\""),
+			'\\' => json.push_str("\\\\"),
+			c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+			c => json.push(c),
+		}
+	}
+	json.push('"');
+}
+
+fn write_value_type(json: &mut String, value_type: &ValueType) {
+	write_string(json, &format!("{}", value_type));
+}
+
+fn external_kind(external: &External) -> &'static str {
+	match *external {
+		External::Function(_) => "function",
+		External::Table(_) => "table",
+		External::Memory(_) => "memory",
+		External::Global(_) => "global",
+		#[cfg(feature = "exceptions")]
+		External::Tag(_) => "tag",
+	}
+}
+
+fn internal_kind(internal: &Internal) -> (&'static str, u32) {
+	match *internal {
+		Internal::Function(index) => ("function", index),
+		Internal::Table(index) => ("table", index),
+		Internal::Memory(index) => ("memory", index),
+		Internal::Global(index) => ("global", index),
+		#[cfg(feature = "exceptions")]
+		Internal::Tag(index) => ("tag", index),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::{ExportEntry, Internal, ValueType};
+	use crate::builder;
+
+	#[test]
+	fn exports_and_signatures() {
+		let module = builder::module()
+			.function()
+			.signature()
+			.with_param(ValueType::I32)
+			.build()
+			.build()
+			.with_export(ExportEntry::new("say \"hi\"".into(), Internal::Function(0)))
+			.build();
+
+		let json = module.to_json();
+		assert!(json.starts_with("{\"sections\":[\"type[1]\","));
+		assert!(json.contains("{\"params\":[\"i32\"],\"results\":[]}"));
+		assert!(json.contains("{\"field\":\"say \\\"hi\\\"\",\"kind\":\"function\",\"index\":0}"));
+		assert!(json.ends_with("\"functions\":[[\"end\"]]}"));
+	}
+}
@@ -1,10 +1,18 @@
 //! Elements of the WebAssembly binary format.
 
 use crate::io;
-use alloc::{string::String, vec::Vec};
+use alloc::{boxed::Box, string::String, vec::Vec};
 
 use core::fmt;
 
+#[cfg(feature = "reduced-stack-buffer")]
+const UNPARSED_BUFFER_LENGTH: usize = 256;
+
+#[cfg(not(feature = "reduced-stack-buffer"))]
+const UNPARSED_BUFFER_LENGTH: usize = 1024;
+
+/// Reads `$length` bytes in chunks of `$buffer_size`, so that a bogus length doesn't cause
+/// a large allocation before the reader runs out of data.
 macro_rules! buffered_read {
 	($buffer_size: expr, $length: expr, $reader: expr) => {{
 		let mut vec_buf = Vec::new();
@@ -24,11 +32,13 @@ macro_rules! buffered_read {
 	}};
 }
 
+mod control_flow;
 mod export_entry;
 mod func;
 mod global_entry;
 mod import_entry;
 mod index_map;
+mod json;
 mod module;
 mod name_section;
 mod ops;
@@ -39,14 +49,19 @@ mod segment;
 mod types;
 
 pub use self::{
+	control_flow::{BasicBlock, ControlFlowGraph},
 	export_entry::{ExportEntry, Internal},
 	global_entry::GlobalEntry,
 	import_entry::{External, GlobalType, ImportEntry, MemoryType, ResizableLimits, TableType},
-	module::{peek_size, ImportCountType, Module},
-	ops::{opcodes, BrTableData, InitExpr, Instruction, Instructions},
+	module::{is_wasm, peek_sections, peek_size, ImportCountType, InstructionVisitor, Module},
+	ops::{
+		opcodes, BrTableData, ConstValue, InitExpr, Instruction, Instructions, ParseConfig,
+		UnknownOpcodeHook, DEFAULT_MAX_BLOCK_DEPTH,
+	},
 	primitives::{
-		CountedList, CountedListWriter, CountedWriter, Uint32, Uint64, Uint8, VarInt32, VarInt64,
-		VarInt7, VarUint1, VarUint32, VarUint64, VarUint7,
+		CountedList, CountedListRefWriter, CountedListWriter, CountedWriter, StreamedCountedWriter,
+		Uint32, Uint64, Uint8, VarInt32, VarInt64, VarInt7, VarUint1, VarUint32, VarUint64,
+		VarUint7,
 	},
 	section::{
 		CodeSection, CustomSection, DataSection, ElementSection, ExportSection, FunctionSection,
@@ -67,6 +82,9 @@ pub use self::ops::SignExtInstruction;
 #[cfg(feature = "bulk")]
 pub use self::ops::BulkInstruction;
 
+#[cfg(feature = "exceptions")]
+pub use self::{import_entry::TagType, ops::ExceptionInstruction, section::TagSection};
+
 #[cfg(any(feature = "simd", feature = "atomics"))]
 pub use self::ops::MemArg;
 
@@ -74,7 +92,8 @@ pub use self::{
 	func::{Func, FuncBody, Local},
 	index_map::IndexMap,
 	name_section::{
-		FunctionNameSubsection, LocalNameSubsection, ModuleNameSubsection, NameMap, NameSection,
+		FunctionNameSubsection, LabelNameSubsection, LocalNameSubsection, ModuleNameSubsection,
+		NameMap, NameSection,
 	},
 	reloc_section::{RelocSection, RelocationEntry},
 	segment::{DataSegment, ElementSegment},
@@ -97,6 +116,29 @@ pub trait Serialize {
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error>;
 }
 
+/// Category of an `Error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+	/// Reading or writing failed, including unexpected end of input.
+	Io,
+	/// Malformed module structure (magic, version, sections, segments, lengths).
+	Format,
+	/// Malformed LEB128 integer.
+	Encoding,
+	/// Non-UTF-8 string.
+	Utf8,
+	/// Unknown opcode.
+	Opcode,
+	/// Unknown value, block, table element or function type.
+	Type,
+	/// Invalid limits or too many locals.
+	Limits,
+	/// Index outside of its index space.
+	Index,
+	/// Any other error.
+	Other,
+}
+
 /// Deserialization/serialization error
 #[derive(Debug, Clone)]
 pub enum Error {
@@ -117,6 +159,9 @@ pub enum Error {
 	Other(&'static str),
 	/// Other allocated error.
 	HeapOther(String),
+	/// I/O error of the underlying reader or writer.
+	#[cfg(feature = "std")]
+	Io(::std::sync::Arc<::std::io::Error>),
 	/// Invalid/unknown value type declaration.
 	UnknownValueType(i8),
 	/// Invalid block type declaration.
@@ -158,12 +203,21 @@ pub enum Error {
 	InvalidTableReference(u8),
 	/// Invalid value used for flags in limits type.
 	InvalidLimitsFlags(u8),
+	/// Maximum of limits is less than the initial size.
+	InvalidLimits {
+		/// Initial size.
+		initial: u32,
+		/// Maximum size.
+		maximum: u32,
+	},
 	/// Unknown function form (should be 0x60).
 	UnknownFunctionForm(u8),
 	/// Invalid varint7 (should be in -64..63 range).
 	InvalidVarInt7(u8),
 	/// Number of function body entries and signatures does not match.
 	InconsistentCode,
+	/// Value of the data count section does not match the number of data segments.
+	InconsistentDataCount,
 	/// Only flags 0, 1, and 2 are accepted on segments.
 	InvalidSegmentFlags(u32),
 	/// Sum of counts of locals is greater than 2^32.
@@ -172,6 +226,70 @@ pub enum Error {
 	DuplicatedNameSubsections(u8),
 	/// Unknown name subsection type.
 	UnknownNameSubsectionType(u8),
+	/// Function index is outside of the function index space.
+	FunctionIndexOutOfBounds {
+		/// Offending index.
+		index: u32,
+		/// Size of the function index space.
+		len: u32,
+	},
+	/// Type index is outside of the type index space.
+	TypeIndexOutOfBounds {
+		/// Offending index.
+		index: u32,
+		/// Size of the type index space.
+		len: u32,
+	},
+	/// Global index is outside of the global index space.
+	GlobalIndexOutOfBounds {
+		/// Offending index.
+		index: u32,
+		/// Size of the global index space.
+		len: u32,
+	},
+	/// Table index is outside of the table index space.
+	TableIndexOutOfBounds {
+		/// Offending index.
+		index: u32,
+		/// Size of the table index space.
+		len: u32,
+	},
+	/// Memory index is outside of the memory index space.
+	MemoryIndexOutOfBounds {
+		/// Offending index.
+		index: u32,
+		/// Size of the memory index space.
+		len: u32,
+	},
+	/// Local index is outside of the local index space.
+	LocalIndexOutOfBounds {
+		/// Offending index.
+		index: u32,
+		/// Size of the local index space.
+		len: u32,
+	},
+	/// Input ended in the middle of a value.
+	TruncatedInput,
+	/// Data left after deserializing the input or a payload.
+	TrailingData,
+	/// Invalid data encountered by the reader.
+	InvalidData,
+	/// Index in a name map is outside of its index space.
+	NameIndexOutOfBounds {
+		/// Offending index.
+		index: u32,
+		/// Size of the index space.
+		len: u32,
+	},
+	/// Indices in a name map are not strictly increasing.
+	NameIndicesOutOfOrder(u32),
+	/// Error occurred while deserializing the input at the given offset.
+	At {
+		/// Number of bytes of the input read when the error occurred.
+		offset: u64,
+		/// The error itself.
+		inner: Box<Error>,
+	},
 }
 
 impl fmt::Display for Error {
@@ -185,6 +303,8 @@ impl fmt::Display for Error {
 			},
 			Error::Other(msg) => write!(f, "{}", msg),
 			Error::HeapOther(ref msg) => write!(f, "{}", msg),
+			#[cfg(feature = "std")]
+			Error::Io(ref err) => write!(f, "I/O error: {}", err),
 			Error::UnknownValueType(ty) => write!(f, "Invalid or unknown value type {}", ty),
 			Error::UnknownBlockType(ty) => write!(f, "Invalid or unknown block type {}", ty),
 			Error::UnknownTableElementType(ty) => write!(f, "Unknown table element type {}", ty),
@@ -209,13 +329,37 @@ impl fmt::Display for Error {
 			Error::InvalidTableReference(ref table_ref) =>
 				write!(f, "Invalid table reference ({})", table_ref),
 			Error::InvalidLimitsFlags(ref flags) => write!(f, "Invalid limits flags ({})", flags),
+			Error::InvalidLimits { initial, maximum } =>
+				write!(f, "Maximum size {} is less than initial size {}", maximum, initial),
 			Error::UnknownFunctionForm(ref form) => write!(f, "Unknown function form ({})", form),
 			Error::InconsistentCode =>
 				write!(f, "Number of function body entries and signatures does not match"),
+			Error::InconsistentDataCount =>
+				write!(f, "Data count section does not match the number of data segments"),
 			Error::InvalidSegmentFlags(n) => write!(f, "Invalid segment flags: {}", n),
 			Error::TooManyLocals => write!(f, "Too many locals"),
 			Error::DuplicatedNameSubsections(n) => write!(f, "Duplicated name subsections: {}", n),
 			Error::UnknownNameSubsectionType(n) => write!(f, "Unknown subsection type: {}", n),
+			Error::FunctionIndexOutOfBounds { index, len } =>
+				write!(f, "Function index {} is out of bounds ({} functions)", index, len),
+			Error::TypeIndexOutOfBounds { index, len } =>
+				write!(f, "Type index {} is out of bounds ({} types)", index, len),
+			Error::GlobalIndexOutOfBounds { index, len } =>
+				write!(f, "Global index {} is out of bounds ({} globals)", index, len),
+			Error::TableIndexOutOfBounds { index, len } =>
+				write!(f, "Table index {} is out of bounds ({} tables)", index, len),
+			Error::MemoryIndexOutOfBounds { index, len } =>
+				write!(f, "Memory index {} is out of bounds ({} memories)", index, len),
+			Error::LocalIndexOutOfBounds { index, len } =>
+				write!(f, "Local index {} is out of bounds ({} locals)", index, len),
+			Error::TruncatedInput => write!(f, "Input ended in the middle of a value"),
+			Error::TrailingData => write!(f, "Unexpected trailing data"),
+			Error::InvalidData => write!(f, "Invalid data"),
+			Error::NameIndexOutOfBounds { index, len } =>
+				write!(f, "Name index {} is out of bounds ({} entries)", index, len),
+			Error::NameIndicesOutOfOrder(index) =>
+				write!(f, "Name index {} is out of order", index),
+			Error::At { offset, ref inner } => write!(f, "{} at byte {}", inner, offset),
 		}
 	}
 }
@@ -230,6 +374,7 @@ impl ::std::error::Error for Error {
 			Error::InconsistentLength { .. } => "Inconsistent length",
 			Error::Other(msg) => msg,
 			Error::HeapOther(ref msg) => &msg[..],
+			Error::Io(_) => "I/O error",
 			Error::UnknownValueType(_) => "Invalid or unknown value type",
 			Error::UnknownBlockType(_) => "Invalid or unknown block type",
 			Error::UnknownTableElementType(_) => "Unknown table element type",
@@ -252,20 +397,147 @@ impl ::std::error::Error for Error {
 			Error::InvalidMemoryReference(_) => "Invalid memory reference",
 			Error::InvalidTableReference(_) => "Invalid table reference",
 			Error::InvalidLimitsFlags(_) => "Invalid limits flags",
+			Error::InvalidLimits { .. } => "Maximum size is less than initial size",
 			Error::UnknownFunctionForm(_) => "Unknown function form",
 			Error::InconsistentCode =>
 				"Number of function body entries and signatures does not match",
+			Error::InconsistentDataCount =>
+				"Data count section does not match the number of data segments",
 			Error::InvalidSegmentFlags(_) => "Invalid segment flags",
 			Error::TooManyLocals => "Too many locals",
 			Error::DuplicatedNameSubsections(_) => "Duplicated name subsections",
 			Error::UnknownNameSubsectionType(_) => "Unknown name subsections type",
+			Error::FunctionIndexOutOfBounds { .. } => "Function index out of bounds",
+			Error::TypeIndexOutOfBounds { .. } => "Type index out of bounds",
+			Error::GlobalIndexOutOfBounds { .. } => "Global index out of bounds",
+			Error::TableIndexOutOfBounds { .. } => "Table index out of bounds",
+			Error::MemoryIndexOutOfBounds { .. } => "Memory index out of bounds",
+			Error::LocalIndexOutOfBounds { .. } => "Local index out of bounds",
+			Error::TruncatedInput => "Truncated input",
+			Error::TrailingData => "Trailing data",
+			Error::InvalidData => "Invalid data",
+			Error::NameIndexOutOfBounds { .. } => "Name index out of bounds",
+			Error::NameIndicesOutOfOrder(_) => "Name indices out of order",
+			#[allow(deprecated)]
+			Error::At { ref inner, .. } => inner.description(),
+		}
+	}
+
+	fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+		match *self {
+			Error::Io(ref err) => Some(&**err),
+			Error::At { ref inner, .. } => inner.source(),
+			_ => None,
+		}
+	}
+}
+
+impl Error {
+	/// Category of the error, to branch on without matching message text.
+	pub fn kind(&self) -> ErrorKind {
+		match *self {
+			Error::UnexpectedEof | Error::TruncatedInput => ErrorKind::Io,
+			#[cfg(feature = "std")]
+			Error::Io(_) => ErrorKind::Io,
+			Error::InvalidMagic |
+			Error::UnsupportedVersion(_) |
+			Error::InconsistentLength { .. } |
+			Error::UnknownExternalKind(_) |
+			Error::UnknownInternalKind(_) |
+			Error::InconsistentMetadata |
+			Error::InvalidSectionId(_) |
+			Error::SectionsOutOfOrder |
+			Error::DuplicatedSections(_) |
+			Error::InvalidMemoryReference(_) |
+			Error::InvalidTableReference(_) |
+			Error::InconsistentCode |
+			Error::InconsistentDataCount |
+			Error::InvalidSegmentFlags(_) |
+			Error::DuplicatedNameSubsections(_) |
+			Error::UnknownNameSubsectionType(_) |
+			Error::TrailingData |
+			Error::InvalidData |
+			Error::NameIndicesOutOfOrder(_) => ErrorKind::Format,
+			Error::InvalidVarUint1(_) |
+			Error::InvalidVarInt32 |
+			Error::InvalidVarInt64 |
+			Error::InvalidVarUint32 |
+			Error::InvalidVarUint64 |
+			Error::InvalidVarInt7(_) => ErrorKind::Encoding,
+			Error::NonUtf8String => ErrorKind::Utf8,
+			Error::UnknownOpcode(_) => ErrorKind::Opcode,
+			#[cfg(feature = "simd")]
+			Error::UnknownSimdOpcode(_) => ErrorKind::Opcode,
+			Error::UnknownValueType(_) |
+			Error::UnknownBlockType(_) |
+			Error::UnknownTableElementType(_) |
+			Error::UnknownFunctionForm(_) => ErrorKind::Type,
+			Error::InvalidLimitsFlags(_) | Error::InvalidLimits { .. } | Error::TooManyLocals =>
+				ErrorKind::Limits,
+			Error::FunctionIndexOutOfBounds { .. } |
+			Error::TypeIndexOutOfBounds { .. } |
+			Error::GlobalIndexOutOfBounds { .. } |
+			Error::TableIndexOutOfBounds { .. } |
+			Error::MemoryIndexOutOfBounds { .. } |
+			Error::LocalIndexOutOfBounds { .. } |
+			Error::NameIndexOutOfBounds { .. } => ErrorKind::Index,
+			Error::At { ref inner, .. } => inner.kind(),
+			Error::Other(_) | Error::HeapOther(_) => ErrorKind::Other,
+		}
+	}
+
+	/// Number of bytes of the input read when the error occurred, if known.
+	///
+	/// This is the offset of [`Error::At`], so it usually points just past the offending
+	/// value rather than at its start.
+	pub fn byte_offset(&self) -> Option<u64> {
+		match *self {
+			Error::At { offset, .. } => Some(offset),
+			_ => None,
+		}
+	}
+
+	/// Index the error is about, for out of bounds or misordered indices.
+	pub fn offending_index(&self) -> Option<u32> {
+		match *self.without_offset() {
+			Error::FunctionIndexOutOfBounds { index, .. } |
+			Error::TypeIndexOutOfBounds { index, .. } |
+			Error::GlobalIndexOutOfBounds { index, .. } |
+			Error::TableIndexOutOfBounds { index, .. } |
+			Error::MemoryIndexOutOfBounds { index, .. } |
+			Error::LocalIndexOutOfBounds { index, .. } |
+			Error::NameIndexOutOfBounds { index, .. } |
+			Error::NameIndicesOutOfOrder(index) => Some(index),
+			_ => None,
+		}
+	}
+
+	/// The error itself, without the byte offset annotation.
+	pub fn without_offset(&self) -> &Error {
+		match *self {
+			Error::At { ref inner, .. } => inner,
+			ref err => err,
+		}
+	}
+
+	/// Annotate the error with the `offset` it occurred at, unless it already is.
+	pub(crate) fn at(self, offset: Option<usize>) -> Self {
+		match (self, offset) {
+			(err @ Error::At { .. }, _) | (err, None) => err,
+			(err, Some(offset)) => Error::At { offset: offset as u64, inner: Box::new(err) },
 		}
 	}
 }
 
 impl From<io::Error> for Error {
 	fn from(err: io::Error) -> Self {
-		Error::HeapOther(format!("I/O Error: {:?}", err))
+		match err {
+			#[cfg(feature = "std")]
+			io::Error::Io(err) => Error::Io(::std::sync::Arc::new(err)),
+			io::Error::UnexpectedEof => Error::TruncatedInput,
+			io::Error::TrailingData => Error::TrailingData,
+			io::Error::InvalidData => Error::InvalidData,
+		}
 	}
 }
 
@@ -287,10 +559,8 @@ impl Deserialize for Unparsed {
 	type Error = Error;
 
 	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
-		let len = VarUint32::deserialize(reader)?.into();
-		let mut vec = vec![0u8; len];
-		reader.read(&mut vec[..])?;
-		Ok(Unparsed(vec))
+		let len: usize = VarUint32::deserialize(reader)?.into();
+		Ok(Unparsed(buffered_read!(UNPARSED_BUFFER_LENGTH, len, reader)))
 	}
 }
 
@@ -312,6 +582,44 @@ pub fn deserialize_buffer<T: Deserialize>(contents: &[u8]) -> Result<T, T::Error
 	Ok(result)
 }
 
+/// Deserialize a module from the start of the buffer, ignoring any bytes following it.
+///
+/// The module extends up to the first byte following a section which is not a section id,
+/// or up to zero padding.
+/// Returns the module together with the number of bytes it occupies. A section running past
+/// the end of the buffer is reported as [`Error::TruncatedInput`].
+pub fn deserialize_buffer_partial(contents: &[u8]) -> Result<(Module, usize), Error> {
+	let size = if is_wasm(contents) { module_size(contents)? } else { contents.len() };
+	let module = deserialize_buffer(&contents[..size])?;
+	Ok((module, size))
+}
+
+/// Size of the module at the start of `source`, which starts with the wasm preamble.
+///
+/// Only the section headers are read, like in [`peek_sections`].
+fn module_size(source: &[u8]) -> Result<usize, Error> {
+	let mut cursor = 8;
+	while let Some(&id) = source.get(cursor) {
+		let known_id = id <= 12 || (cfg!(feature = "exceptions") && id == 13);
+		// A custom section always holds at least its name, so a zero byte followed by another
+		// zero byte or by the end of the buffer is padding rather than a section header.
+		let padding = id == 0 && source.get(cursor + 1).map_or(true, |&byte| byte == 0);
+		if !known_id || padding {
+			break
+		}
+		let mut header = io::Cursor::new(&source[cursor + 1..]);
+		let len: usize = VarUint32::deserialize(&mut header)
+			.map_err(|err| err.at(Some(cursor + 1)))?
+			.into();
+		let payload_start = cursor + 1 + header.position();
+		if source.len() - payload_start < len {
+			return Err(Error::TruncatedInput.at(Some(payload_start)))
+		}
+		cursor = payload_start + len;
+	}
+	Ok(cursor)
+}
+
 /// Create buffer with serialized value.
 pub fn serialize<T: Serialize>(val: T) -> Result<Vec<u8>, T::Error> {
 	let mut buf = Vec::new();
@@ -319,13 +627,23 @@ pub fn serialize<T: Serialize>(val: T) -> Result<Vec<u8>, T::Error> {
 	Ok(buf)
 }
 
+/// Deserialize module from the serial reader.
+pub fn deserialize_from<R: io::Read>(reader: &mut R) -> Result<Module, Error> {
+	Module::deserialize(reader)
+}
+
+/// Serialize module to the serial writer.
+pub fn serialize_to<W: io::Write>(writer: &mut W, module: Module) -> Result<(), Error> {
+	module.serialize(writer)
+}
+
 /// Deserialize module from the file.
 #[cfg(feature = "std")]
 pub fn deserialize_file<P: AsRef<::std::path::Path>>(p: P) -> Result<Module, Error> {
-	let mut f = ::std::fs::File::open(p)
+	let f = ::std::fs::File::open(p)
 		.map_err(|e| Error::HeapOther(format!("Can't read from the file: {:?}", e)))?;
 
-	Module::deserialize(&mut f)
+	deserialize_from(&mut io::TrackingReader::new(f))
 }
 
 /// Serialize module to the file
@@ -334,6 +652,136 @@ pub fn serialize_to_file<P: AsRef<::std::path::Path>>(p: P, module: Module) -> R
 	let mut io = ::std::fs::File::create(p)
 		.map_err(|e| Error::HeapOther(format!("Can't create the file: {:?}", e)))?;
 
-	module.serialize(&mut io)?;
-	Ok(())
+	serialize_to(&mut io, module)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		deserialize_buffer, deserialize_buffer_partial, io, serialize, Error, ErrorKind, Module,
+		VarUint32,
+	};
+
+	#[test]
+	fn error_kind() {
+		assert_eq!(Error::InvalidMagic.kind(), ErrorKind::Format);
+		assert_eq!(Error::InvalidVarUint32.kind(), ErrorKind::Encoding);
+		assert_eq!(Error::UnknownOpcode(0x27).kind(), ErrorKind::Opcode);
+		assert_eq!(Error::TooManyLocals.kind(), ErrorKind::Limits);
+		assert_eq!(Error::Other("custom").kind(), ErrorKind::Other);
+		assert_eq!(Error::from(io::Error::UnexpectedEof).kind(), ErrorKind::Io);
+		assert_eq!(
+			deserialize_buffer::<VarUint32>(&[0x80, 0x80]).unwrap_err().kind(),
+			ErrorKind::Io
+		);
+		assert_eq!(
+			deserialize_buffer::<Module>(&[0x00, 0x61, 0x73, 0x6e]).unwrap_err().kind(),
+			ErrorKind::Format
+		);
+	}
+	#[cfg(feature = "std")]
+	#[test]
+	fn io_error_source() {
+		use super::Deserialize;
+		use std::error::Error as _;
+
+		let err = VarUint32::deserialize(&mut &[0x80][..]).unwrap_err();
+		assert_eq!(err.kind(), ErrorKind::Io);
+		let source = err.source().expect("I/O error should be the source");
+		let io_err = source.downcast_ref::<std::io::Error>().expect("Should be std::io::Error");
+		assert_eq!(io_err.kind(), std::io::ErrorKind::UnexpectedEof);
+		assert!(Error::InvalidMagic.source().is_none());
+	}
+	#[cfg(feature = "std")]
+	#[test]
+	fn io_error_source_at_offset() {
+		use super::deserialize_file;
+		use std::error::Error as _;
+
+		let bytes = std::fs::read("./res/cases/v1/test5.wasm").expect("Should be read");
+		let path = std::env::temp_dir().join("parity-wasm-io-error-source-truncated.wasm");
+		std::fs::write(&path, &bytes[..bytes.len() / 2]).expect("Should be written");
+		let err = deserialize_file(&path).unwrap_err();
+		std::fs::remove_file(&path).expect("Should be removed");
+
+		assert!(err.byte_offset().is_some());
+		let source = err.source().expect("I/O error should be the source");
+		let io_err = source.downcast_ref::<std::io::Error>().expect("Should be std::io::Error");
+		assert_eq!(io_err.kind(), std::io::ErrorKind::UnexpectedEof);
+	}
+	#[test]
+	fn structured_errors() {
+		assert!(matches!(Error::from(io::Error::TrailingData), Error::TrailingData));
+		assert!(matches!(deserialize_buffer::<VarUint32>(&[0x01, 0x02]), Err(Error::TrailingData)));
+		assert!(matches!(deserialize_buffer::<VarUint32>(&[0x80]), Err(Error::TruncatedInput)));
+	}
+	#[test]
+	fn error_accessors() {
+		let err = Error::TypeIndexOutOfBounds { index: 7, len: 2 };
+		assert_eq!(err.byte_offset(), None);
+		assert_eq!(err.offending_index(), Some(7));
+
+		let err = err.at(Some(42));
+		assert_eq!(err.byte_offset(), Some(42));
+		assert_eq!(err.offending_index(), Some(7));
+		assert!(matches!(err.without_offset(), Error::TypeIndexOutOfBounds { index: 7, len: 2 }));
+
+		assert_eq!(Error::NameIndicesOutOfOrder(3).offending_index(), Some(3));
+		assert_eq!(Error::InvalidMagic.offending_index(), None);
+
+		let bytes = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x0f];
+		let err = deserialize_buffer::<Module>(&bytes).unwrap_err();
+		assert_eq!(err.byte_offset(), Some(9));
+		assert!(matches!(err.without_offset(), Error::InvalidSectionId(0x0f)));
+	}
+
+	#[test]
+	fn deserialize_partial() {
+		let module = crate::builder::module().function().signature().build().build().build();
+		let mut bytes = serialize(module.clone()).expect("serialization to succeed");
+		let len = bytes.len();
+		bytes.extend_from_slice(&[0xff, 0x00, 0x00, 0x00]);
+
+		assert!(deserialize_buffer::<Module>(&bytes).is_err());
+		let (partial, consumed) =
+			deserialize_buffer_partial(&bytes).expect("Should be deserialized");
+		assert_eq!(consumed, len);
+		assert_eq!(partial, module);
+
+		let (empty, consumed) =
+			deserialize_buffer_partial(&bytes[..8]).expect("Should be deserialized");
+		assert_eq!(consumed, 8);
+		assert_eq!(empty, Module::default());
+	}
+
+	#[test]
+	fn deserialize_partial_truncated() {
+		let mut module = crate::builder::module().function().signature().build().build().build();
+		module.set_custom_section("trailing", vec![1, 2, 3]);
+		let bytes = serialize(module).expect("serialization to succeed");
+
+		let err = deserialize_buffer_partial(&bytes[..bytes.len() - 1]).unwrap_err();
+		assert!(matches!(err.without_offset(), Error::TruncatedInput));
+
+		// A section header cut in the middle of the length.
+		let mut bytes = serialize(Module::default()).expect("serialization to succeed");
+		bytes.extend_from_slice(&[0x00, 0x80]);
+		let err = deserialize_buffer_partial(&bytes).unwrap_err();
+		assert!(matches!(err.without_offset(), Error::TruncatedInput));
+	}
+
+	#[test]
+	fn deserialize_partial_zero_padding() {
+		let module = crate::builder::module().function().signature().build().build().build();
+		let bytes = serialize(module.clone()).expect("serialization to succeed");
+
+		for padding in 1..=5 {
+			let mut padded = bytes.clone();
+			padded.resize(bytes.len() + padding, 0);
+			let (partial, consumed) =
+				deserialize_buffer_partial(&padded).expect("Should be deserialized");
+			assert_eq!(consumed, bytes.len());
+			assert_eq!(partial, module);
+		}
+	}
 }
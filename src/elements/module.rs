@@ -1,18 +1,29 @@
 use crate::io;
-use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use alloc::{borrow::ToOwned, collections::BTreeMap, string::String, vec::Vec};
 
 use super::{
 	deserialize_buffer,
+	index_map::IndexMap,
 	name_section::NameSection,
-	reloc_section::RelocSection,
 	section::{
 		CodeSection, CustomSection, DataSection, ElementSection, ExportSection, FunctionSection,
 		GlobalSection, ImportSection, MemorySection, Section, TableSection, TypeSection,
 	},
-	serialize, Deserialize, Error, External, Serialize, Uint32,
+	serialize, ConstValue, Deserialize, Error, External, FunctionType, GlobalType, ImportEntry,
+	InitExpr, Instruction, Internal, Local, ParseConfig, Serialize, Type, Uint32,
+	UnknownOpcodeHook, ValueType,
 };
 
-use core::cmp;
+#[cfg(feature = "exceptions")]
+use super::section::TagSection;
+
+#[cfg(feature = "multi_value")]
+use super::BlockType;
+
+#[cfg(all(feature = "multi_value", feature = "exceptions"))]
+use super::ExceptionInstruction;
+
+use core::{cmp, fmt};
 
 const WASM_MAGIC_NUMBER: [u8; 4] = [0x00, 0x61, 0x73, 0x6d];
 
@@ -35,6 +46,15 @@ pub enum ImportCountType {
 	Table,
 	/// Count memories
 	Memory,
+	/// Count exception tags
+	#[cfg(feature = "exceptions")]
+	Tag,
+}
+
+/// Visitor applied to instructions of a module by [`Module::visit_instructions`].
+pub trait InstructionVisitor {
+	/// Visit (and possibly rewrite) a single instruction.
+	fn visit(&mut self, instr: &mut Instruction);
 }
 
 impl Default for Module {
@@ -47,6 +67,14 @@ impl Default for Module {
 	}
 }
 
+impl TryFrom<&[u8]> for Module {
+	type Error = Error;
+
+	fn try_from(input: &[u8]) -> Result<Self, Error> {
+		Module::from_bytes(input)
+	}
+}
+
 impl Module {
 	/// New module with sections
 	pub fn new(sections: Vec<Section>) -> Self {
@@ -54,6 +82,19 @@ impl Module {
 	}
 
 	/// Construct a module from a slice.
+	///
+	/// Also available as `Module::try_from(&[u8])`.
+	///
+	/// # Examples
+	/// ```
+	/// use parity_wasm::elements::Module;
+	///
+	/// // magic, version and an empty type section
+	/// let bytes = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00];
+	/// let module = Module::from_bytes(&bytes).expect("valid module");
+	/// assert_eq!(module.type_section().map(|ts| ts.types().len()), Some(0));
+	/// assert!(Module::try_from(&bytes[..4]).is_err());
+	/// ```
 	pub fn from_bytes<T: AsRef<[u8]>>(input: T) -> Result<Self, Error> {
 		deserialize_buffer::<Module>(input.as_ref())
 	}
@@ -63,16 +104,49 @@ impl Module {
 		serialize::<Module>(self)
 	}
 
+	/// Size in bytes of the serialized module.
+	///
+	/// The sections are serialized by reference into a sink which only counts the bytes, so
+	/// neither the module nor the output is copied. Only name and relocation sections are
+	/// cloned to be serialized.
+	pub fn serialized_size(&self) -> Result<usize, Error> {
+		let mut writer = io::CountingWriter::new();
+		Uint32::from(self.magic).serialize(&mut writer)?;
+		Uint32::from(self.version).serialize(&mut writer)?;
+		for section in self.sections() {
+			section.serialize(&mut writer)?;
+		}
+		Ok(writer.count())
+	}
+
 	/// Destructure the module, yielding sections
 	pub fn into_sections(self) -> Vec<Section> {
 		self.sections
 	}
 
+	/// Module from sections, the inverse of `into_sections`.
+	///
+	/// Fails if known sections are out of order or appear more than once.
+	pub fn from_sections(sections: Vec<Section>) -> Result<Self, Error> {
+		let mut last_section_order = 0;
+		for section in &sections {
+			check_section_order(&mut last_section_order, section)?;
+		}
+		Ok(Module::new(sections))
+	}
+
 	/// Version of module.
 	pub fn version(&self) -> u32 {
 		self.version
 	}
 
+	/// Set version of module.
+	///
+	/// Note that only version `1` modules can be deserialized back.
+	pub fn set_version(&mut self, version: u32) {
+		self.version = version;
+	}
+
 	/// Sections list.
 	///
 	/// Each known section is optional and may appear at most once.
@@ -113,6 +187,65 @@ impl Module {
 		Ok(())
 	}
 
+	/// First section with the given binary format id, if any.
+	///
+	/// See [`Section::id`]; id `0` matches the first custom section.
+	pub fn first_section_by_id(&self, id: u8) -> Option<&Section> {
+		self.sections().iter().find(|section| section.id() == id)
+	}
+
+	/// First section with the given binary format id, if any (mutable).
+	pub fn first_section_by_id_mut(&mut self, id: u8) -> Option<&mut Section> {
+		self.sections_mut().iter_mut().find(|section| section.id() == id)
+	}
+
+	/// Sort the known sections into their canonical order.
+	///
+	/// Custom sections stay attached to the known section preceding them, and the sort is
+	/// stable, so a well-ordered module is left unchanged. This is done automatically when
+	/// the module is serialized, unless [`Module::serialize_unordered`] is used.
+	pub fn order_sections(&mut self) {
+		let mut groups: Vec<(u8, Vec<Section>)> = vec![(0, Vec::new())];
+		for section in self.sections.drain(..) {
+			match section.order() {
+				0 => groups.last_mut().expect("groups start non-empty; qed").1.push(section),
+				order => groups.push((order, vec![section])),
+			}
+		}
+		groups.sort_by_key(|&(order, _)| order);
+		self.sections = groups.into_iter().flat_map(|(_, sections)| sections).collect();
+	}
+
+	/// Bring the module into a canonical form for reproducible output.
+	///
+	/// Custom sections whose name is not in `allowed_custom` are dropped (parsed name and
+	/// relocation sections included), the known sections are ordered with
+	/// [`Module::order_sections`] and the remaining custom sections are moved to the end,
+	/// in the order of `allowed_custom`.
+	pub fn canonicalize(&mut self, allowed_custom: &[&str]) {
+		let rank = |section: &Section| {
+			let name = match *section {
+				Section::Custom(ref custom) => custom.name(),
+				Section::Name(_) => "name",
+				Section::Reloc(ref reloc) => reloc.name(),
+				_ => return None,
+			};
+			Some(allowed_custom.iter().position(|allowed| *allowed == name))
+		};
+
+		let mut custom = Vec::new();
+		for section in core::mem::take(&mut self.sections) {
+			match rank(&section) {
+				None => self.sections.push(section),
+				Some(Some(rank)) => custom.push((rank, section)),
+				Some(None) => {},
+			}
+		}
+		self.order_sections();
+		custom.sort_by_key(|&(rank, _)| rank);
+		self.sections.extend(custom.into_iter().map(|(_, section)| section));
+	}
+
 	/// Code section reference, if any.
 	pub fn code_section(&self) -> Option<&CodeSection> {
 		for section in self.sections() {
@@ -253,6 +386,33 @@ impl Module {
 		None
 	}
 
+	/// Data count section, if any.
+	pub fn data_count_section(&self) -> Option<u32> {
+		for section in self.sections() {
+			if let Section::DataCount(count) = *section {
+				return Some(count)
+			}
+		}
+		None
+	}
+
+	/// Insert a data count section matching the number of data segments, or update the
+	/// existing one.
+	///
+	/// The section is placed before the code section, as required for `memory.init` and
+	/// `data.drop` to be valid.
+	pub fn ensure_data_count_section(&mut self) {
+		let count = self.data_segment_count() as u32;
+		for section in self.sections_mut() {
+			if let Section::DataCount(ref mut existing) = *section {
+				*existing = count;
+				return
+			}
+		}
+		self.insert_section(Section::DataCount(count))
+			.expect("data count section was checked to be absent; qed");
+	}
+
 	/// Element section reference, if any.
 	pub fn elements_section(&self) -> Option<&ElementSection> {
 		for section in self.sections() {
@@ -293,6 +453,28 @@ impl Module {
 		None
 	}
 
+	/// Exception tags section reference, if any.
+	#[cfg(feature = "exceptions")]
+	pub fn tag_section(&self) -> Option<&TagSection> {
+		for section in self.sections() {
+			if let Section::Tag(ref section) = *section {
+				return Some(section)
+			}
+		}
+		None
+	}
+
+	/// Exception tags section mutable reference, if any.
+	#[cfg(feature = "exceptions")]
+	pub fn tag_section_mut(&mut self) -> Option<&mut TagSection> {
+		for section in self.sections_mut() {
+			if let Section::Tag(ref mut section) = *section {
+				return Some(section)
+			}
+		}
+		None
+	}
+
 	/// Functions signatures section reference, if any.
 	pub fn function_section(&self) -> Option<&FunctionSection> {
 		for section in self.sections() {
@@ -441,6 +623,44 @@ impl Module {
 		None
 	}
 
+	/// Name section of the module, parsing the `name` custom section if needed.
+	///
+	/// Returns `None` if there is no name section or it cannot be decoded, see
+	/// [`try_names`](Self::try_names) to get the decoding error.
+	pub fn names(&self) -> Option<NameSection> {
+		self.try_names().ok().flatten()
+	}
+
+	/// Name section of the module, parsing the `name` custom section if needed.
+	///
+	/// Unlike [`parse_names`](Self::parse_names) the module is left untouched.
+	pub fn try_names(&self) -> Result<Option<NameSection>, Error> {
+		for section in self.sections() {
+			match *section {
+				Section::Name(ref name_section) => return Ok(Some(name_section.clone())),
+				Section::Custom(ref custom) if custom.name() == "name" => {
+					let mut rdr = io::Cursor::new(custom.payload());
+					return NameSection::deserialize(self, &mut rdr).map(Some)
+				},
+				_ => {},
+			}
+		}
+		Ok(None)
+	}
+
+	/// Replace the name section of the module, parsed or not, or add one.
+	pub fn set_names(&mut self, names: NameSection) {
+		let existing = self.sections_mut().iter_mut().find(|section| match **section {
+			Section::Name(_) => true,
+			Section::Custom(ref custom) => custom.name() == "name",
+			_ => false,
+		});
+		match existing {
+			Some(section) => *section = Section::Name(names),
+			None => self.sections_mut().push(Section::Name(names)),
+		}
+	}
+
 	/// Try to parse name section in place.
 	///
 	/// Corresponding custom section with proper header will convert to name sections
@@ -493,31 +713,14 @@ impl Module {
 		let mut parse_errors = Vec::new();
 
 		for (i, section) in self.sections.iter_mut().enumerate() {
-			if let Some(relocation_section) = {
-				if let Section::Custom(ref custom) = *section {
-					if custom.name().starts_with("reloc.") {
-						let mut rdr = io::Cursor::new(custom.payload());
-						let reloc_section =
-							match RelocSection::deserialize(custom.name().to_owned(), &mut rdr) {
-								Ok(reloc_section) => reloc_section,
-								Err(e) => {
-									parse_errors.push((i, e));
-									continue
-								},
-							};
-						if rdr.position() != custom.payload().len() {
-							parse_errors.push((i, io::Error::InvalidData.into()));
-							continue
-						}
-						Some(Section::Reloc(reloc_section))
-					} else {
-						None
-					}
-				} else {
-					None
-				}
-			} {
-				*section = relocation_section;
+			let parsed = match *section {
+				Section::Custom(ref custom) => custom.parse_reloc(),
+				_ => None,
+			};
+			match parsed {
+				Some(Ok(reloc_section)) => *section = Section::Reloc(reloc_section),
+				Some(Err(e)) => parse_errors.push((i, e)),
+				None => {},
 			}
 		}
 
@@ -534,190 +737,1477 @@ impl Module {
 			.map(|is| {
 				is.entries()
 					.iter()
-					.filter(|import| {
-						matches!(
-							(count_type, *import.external()),
-							(ImportCountType::Function, External::Function(_)) |
-								(ImportCountType::Global, External::Global(_)) |
-								(ImportCountType::Table, External::Table(_)) |
-								(ImportCountType::Memory, External::Memory(_))
-						)
+					.filter(|import| match (count_type, *import.external()) {
+						(ImportCountType::Function, External::Function(_)) |
+						(ImportCountType::Global, External::Global(_)) |
+						(ImportCountType::Table, External::Table(_)) |
+						(ImportCountType::Memory, External::Memory(_)) => true,
+						#[cfg(feature = "exceptions")]
+						(ImportCountType::Tag, External::Tag(_)) => true,
+						_ => false,
 					})
 					.count()
 			})
 			.unwrap_or(0)
 	}
 
-	/// Query functions space.
-	pub fn functions_space(&self) -> usize {
-		self.import_count(ImportCountType::Function) +
-			self.function_section().map(|fs| fs.entries().len()).unwrap_or(0)
-	}
-
-	/// Query globals space.
-	pub fn globals_space(&self) -> usize {
-		self.import_count(ImportCountType::Global) +
-			self.global_section().map(|gs| gs.entries().len()).unwrap_or(0)
+	/// Types of the imported globals, in the order of the globals space.
+	pub fn imported_globals(&self) -> Vec<&GlobalType> {
+		self.import_section()
+			.map(|is| {
+				is.entries()
+					.iter()
+					.filter_map(|import| match *import.external() {
+						External::Global(ref global_type) => Some(global_type),
+						_ => None,
+					})
+					.collect()
+			})
+			.unwrap_or_default()
 	}
 
-	/// Query table space.
-	pub fn table_space(&self) -> usize {
-		self.import_count(ImportCountType::Table) +
-			self.table_section().map(|ts| ts.entries().len()).unwrap_or(0)
-	}
+	/// Turn the defined memory into a memory imported as `module_name`.`field`.
+	///
+	/// The memory section is removed. The memory index stays `0`, so nothing else
+	/// needs to be updated. Fails unless the module defines exactly one memory and
+	/// imports none.
+	pub fn export_memory_as_import(&mut self, module_name: &str, field: &str) -> Result<(), Error> {
+		if self.import_count(ImportCountType::Memory) != 0 {
+			return Err(Error::Other("Module already imports a memory"))
+		}
+		let memory = match self.memory_section().map(|ms| ms.entries()) {
+			Some([memory]) => *memory,
+			_ => return Err(Error::Other("Module should define exactly one memory")),
+		};
 
-	/// Query memory space.
-	pub fn memory_space(&self) -> usize {
-		self.import_count(ImportCountType::Memory) +
-			self.memory_section().map(|ms| ms.entries().len()).unwrap_or(0)
+		self.sections.retain(|section| !matches!(*section, Section::Memory(_)));
+		let entry = ImportEntry::new(module_name.into(), field.into(), External::Memory(memory));
+		match self.import_section_mut() {
+			Some(is) => is.entries_mut().push(entry),
+			None =>
+				self.insert_section(Section::Import(ImportSection::with_entries(vec![entry])))?,
+		}
+		Ok(())
 	}
-}
-
-impl Deserialize for Module {
-	type Error = super::Error;
 
-	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
-		let mut sections = Vec::new();
-
-		let mut magic = [0u8; 4];
-		reader.read(&mut magic)?;
-		if magic != WASM_MAGIC_NUMBER {
-			return Err(Error::InvalidMagic)
+	/// Turn the imported memory into a memory defined by the module.
+	///
+	/// The inverse of `export_memory_as_import`. The import section is removed if it
+	/// becomes empty. Fails unless the module imports exactly one memory and defines none.
+	pub fn internalize_memory(&mut self) -> Result<(), Error> {
+		if self.memory_section().map_or(false, |ms| !ms.entries().is_empty()) {
+			return Err(Error::Other("Module already defines a memory"))
 		}
-
-		let version: u32 = Uint32::deserialize(reader)?.into();
-
-		if version != 1 {
-			return Err(Error::UnsupportedVersion(version))
+		if self.import_count(ImportCountType::Memory) != 1 {
+			return Err(Error::Other("Module should import exactly one memory"))
 		}
 
-		let mut last_section_order = 0;
-
-		loop {
-			match Section::deserialize(reader) {
-				Err(Error::UnexpectedEof) => break,
-				Err(e) => return Err(e),
-				Ok(section) => {
-					if section.order() != 0 {
-						match last_section_order {
-							x if x > section.order() => return Err(Error::SectionsOutOfOrder),
-							x if x == section.order() =>
-								return Err(Error::DuplicatedSections(last_section_order)),
-							_ => {},
-						};
-
-						last_section_order = section.order();
-					}
-					sections.push(section);
-				},
-			}
+		let entries = self.import_section_mut().expect("memory is imported; qed").entries_mut();
+		let position = entries
+			.iter()
+			.position(|entry| matches!(*entry.external(), External::Memory(_)))
+			.expect("memory is imported; qed");
+		let memory = match *entries.remove(position).external() {
+			External::Memory(memory) => memory,
+			_ => unreachable!("position is of a memory import; qed"),
+		};
+		if entries.is_empty() {
+			self.sections.retain(|section| !matches!(*section, Section::Import(_)));
 		}
 
-		let module = Module { magic: u32::from_le_bytes(magic), version, sections };
+		self.sections.retain(|section| !matches!(*section, Section::Memory(_)));
+		self.insert_section(Section::Memory(MemorySection::with_entries(vec![memory])))
+	}
 
-		if module.code_section().map(|cs| cs.bodies().len()).unwrap_or(0) !=
-			module.function_section().map(|fs| fs.entries().len()).unwrap_or(0)
+	/// Remove data, element and data count sections, leaving code and interface only.
+	///
+	/// Fails, leaving the module untouched, if any function body refers to segments
+	/// through bulk memory instructions.
+	pub fn strip_initializers(&mut self) -> Result<(), Error> {
+		#[cfg(feature = "bulk")]
 		{
-			return Err(Error::InconsistentCode)
+			use super::BulkInstruction::{MemoryDrop, MemoryInit, TableDrop, TableInit};
+
+			let uses_segments = self.code_section().map_or(false, |cs| {
+				cs.bodies().iter().flat_map(|body| body.code().elements()).any(|instruction| {
+					matches!(
+						*instruction,
+						Instruction::Bulk(
+							MemoryInit(_) | MemoryDrop(_) | TableInit(_) | TableDrop(_)
+						)
+					)
+				})
+			});
+			if uses_segments {
+				return Err(Error::Other("Function bodies refer to data or element segments"))
+			}
 		}
 
-		Ok(module)
+		self.sections.retain(|section| {
+			!matches!(*section, Section::Data(_) | Section::Element(_) | Section::DataCount(_))
+		});
+		Ok(())
 	}
-}
 
-impl Serialize for Module {
-	type Error = Error;
+	/// Remove a defined function, shifting down all references to functions after it.
+	///
+	/// Call targets, function exports, element segment members and the start function
+	/// are updated, and the names of a parsed name section are moved along. Fails if the
+	/// function is imported, does not exist, or is still referenced from any of those
+	/// places (other than its own body).
+	pub fn remove_function(&mut self, func_index: u32) -> Result<(), Error> {
+		let imported_functions = self.import_count(ImportCountType::Function) as u32;
+		if func_index < imported_functions {
+			return Err(Error::Other("Imported functions cannot be removed"))
+		}
+		let defined_index = (func_index - imported_functions) as usize;
+		if defined_index >= self.function_section().map(|fs| fs.entries().len()).unwrap_or(0) {
+			return Err(Error::FunctionIndexOutOfBounds {
+				index: func_index,
+				len: self.functions_space() as u32,
+			})
+		}
+		if self.is_function_referenced(func_index, defined_index) {
+			return Err(Error::Other("Removed function is still referenced"))
+		}
 
-	fn serialize<W: io::Write>(self, w: &mut W) -> Result<(), Self::Error> {
-		Uint32::from(self.magic).serialize(w)?;
-		Uint32::from(self.version).serialize(w)?;
-		for section in self.sections.into_iter() {
-			// todo: according to the spec the name section should appear after the data section
-			section.serialize(w)?;
+		if let Some(fs) = self.function_section_mut() {
+			fs.entries_mut().remove(defined_index);
+		}
+		if let Some(cs) = self.code_section_mut() {
+			if defined_index < cs.bodies().len() {
+				cs.bodies_mut().remove(defined_index);
+			}
 		}
+
+		self.map_function_indices(
+			|index| if index > func_index { index - 1 } else { index },
+			&[func_index],
+		);
 		Ok(())
 	}
-}
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-struct PeekSection<'a> {
-	cursor: usize,
-	region: &'a [u8],
-}
+	/// Merge defined functions with the same signature and identical bodies.
+	///
+	/// Every reference to a duplicate is redirected to the first function with the same
+	/// body, then the duplicates are removed and the indices of the following functions
+	/// shifted down as in [`remove_function`](Self::remove_function). Locals are compared
+	/// after merging adjacent declarations of the same type.
+	///
+	/// Returns the number of removed functions.
+	pub fn deduplicate_functions(&mut self) -> usize {
+		let imported_functions = self.import_count(ImportCountType::Function) as u32;
+		let bodies = match (self.function_section(), self.code_section()) {
+			(Some(fs), Some(cs)) if fs.entries().len() == cs.bodies().len() => cs.bodies(),
+			_ => return 0,
+		};
 
-impl<'a> io::Read for PeekSection<'a> {
-	fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
-		let available = cmp::min(buf.len(), self.region.len() - self.cursor);
-		if available < buf.len() {
-			return Err(io::Error::UnexpectedEof)
+		// New index of every defined function, and defined indices of the kept ones.
+		let mut remap = Vec::with_capacity(bodies.len());
+		let mut kept: Vec<usize> = Vec::new();
+		for (i, body) in bodies.iter().enumerate() {
+			let signature = self.function_type_of(imported_functions + i as u32);
+			let locals = merged_locals(body.locals());
+			let original = kept.iter().position(|&j| {
+				bodies[j].code() == body.code() &&
+					merged_locals(bodies[j].locals()) == locals &&
+					self.function_type_of(imported_functions + j as u32) == signature
+			});
+			match original {
+				Some(position) => remap.push(imported_functions + position as u32),
+				None => {
+					remap.push(imported_functions + kept.len() as u32);
+					kept.push(i);
+				},
+			}
+		}
+		let bodies_len = bodies.len();
+		let removed = bodies_len - kept.len();
+		if removed == 0 {
+			return 0
 		}
 
-		let range = self.cursor..self.cursor + buf.len();
-		buf.copy_from_slice(&self.region[range]);
+		fn retain_kept<T>(items: &mut Vec<T>, kept: &[usize]) {
+			let all = core::mem::take(items);
+			items.extend(
+				all.into_iter()
+					.enumerate()
+					.filter(|(i, _)| kept.binary_search(i).is_ok())
+					.map(|(_, item)| item),
+			);
+		}
+		if let Some(fs) = self.function_section_mut() {
+			retain_kept(fs.entries_mut(), &kept);
+		}
+		if let Some(cs) = self.code_section_mut() {
+			retain_kept(cs.bodies_mut(), &kept);
+		}
+		let duplicates: Vec<u32> = (0..bodies_len)
+			.filter(|i| kept.binary_search(i).is_err())
+			.map(|i| imported_functions + i as u32)
+			.collect();
+		self.map_function_indices(
+			|index| match index.checked_sub(imported_functions) {
+				None => index,
+				Some(defined) => match remap.get(defined as usize) {
+					Some(&new_index) => new_index,
+					None => index - removed as u32,
+				},
+			},
+			&duplicates,
+		);
 
-		self.cursor += available;
-		Ok(())
+		removed
 	}
-}
 
-/// Returns size of the module in the provided stream.
-pub fn peek_size(source: &[u8]) -> usize {
-	if source.len() < 9 {
-		return 0
+	/// Apply an arbitrary mapping of function indices to all references in the module.
+	///
+	/// Call targets, function exports, element segment members, the start function and the
+	/// names of a parsed name section are rewritten; type indices of `call_indirect` are left
+	/// untouched. Imports, the function section and the code section are not reordered, so
+	/// this is only consistent if the caller moves the definitions accordingly.
+	pub fn remap_functions(&mut self, map: &dyn Fn(u32) -> u32) {
+		self.map_function_indices(map, &[]);
 	}
 
-	let mut cursor = 8;
-	loop {
-		let (new_cursor, section_id, section_len) = {
-			let mut peek_section = PeekSection { cursor: 0, region: &source[cursor..] };
-			let section_id: u8 = match super::VarUint7::deserialize(&mut peek_section) {
-				Ok(res) => res.into(),
-				Err(_) => break,
-			};
-			let section_len: u32 = match super::VarUint32::deserialize(&mut peek_section) {
-				Ok(res) => res.into(),
-				Err(_) => break,
-			};
-
-			(peek_section.cursor, section_id, section_len)
-		};
-
-		if section_id <= 11 && section_len > 0 {
-			let next_cursor = cursor + new_cursor + section_len as usize;
+	/// Rewrite call targets, function exports, element segment members and the start
+	/// function with `f`.
+	///
+	/// Function, local and label names of a parsed name section are moved to the new
+	/// indices, except for the names of the `removed` functions, which are dropped. `removed`
+	/// holds the old indices in increasing order.
+	fn map_function_indices<F: Fn(u32) -> u32>(&mut self, f: F, removed: &[u32]) {
+		fn map_keys<T>(names: &mut IndexMap<T>, f: &dyn Fn(u32) -> u32, removed: &[u32]) {
+			let entries = core::mem::replace(names, IndexMap::with_capacity(0));
+			*names = entries
+				.into_iter()
+				.filter(|(index, _)| removed.binary_search(index).is_err())
+				.map(|(index, value)| (f(index), value))
+				.collect();
+		}
 
-			match next_cursor {
-				x if x > source.len() => break,
-				x if x == source.len() => {
-					cursor = next_cursor;
-					break
+		let map = |index: &mut u32| *index = f(*index);
+		for section in self.sections_mut() {
+			match *section {
+				Section::Code(ref mut cs) =>
+					for body in cs.bodies_mut() {
+						for instruction in body.code_mut().elements_mut() {
+							if let Instruction::Call(ref mut index) = *instruction {
+								map(index);
+							}
+						}
+					},
+				Section::Export(ref mut es) =>
+					for entry in es.entries_mut() {
+						if let Internal::Function(ref mut index) = *entry.internal_mut() {
+							map(index);
+						}
+					},
+				Section::Element(ref mut es) =>
+					for segment in es.entries_mut() {
+						segment.members_mut().iter_mut().for_each(map);
+					},
+				Section::Start(ref mut index) => map(index),
+				Section::Name(ref mut ns) => {
+					if let Some(ref mut functions) = *ns.functions_mut() {
+						map_keys(functions.names_mut(), &f, removed);
+					}
+					if let Some(ref mut locals) = *ns.locals_mut() {
+						map_keys(locals.local_names_mut(), &f, removed);
+					}
+					if let Some(ref mut labels) = *ns.labels_mut() {
+						map_keys(labels.label_names_mut(), &f, removed);
+					}
 				},
 				_ => {},
 			}
-			cursor = next_cursor;
-		} else {
-			break
 		}
 	}
 
-	cursor
-}
-
-#[cfg(test)]
+	/// Indices of the type section entries which are not referenced in the module.
+	///
+	/// Signatures of imported and defined functions, `call_indirect` instructions and,
+	/// depending on the enabled features, block types and tags count as references.
+	pub fn unused_type_indices(&self) -> Vec<u32> {
+		let types = self.type_section().map_or(0, |ts| ts.types().len());
+		let mut used = vec![false; types];
+		let mut mark = |type_ref: u32| {
+			if let Some(used) = used.get_mut(type_ref as usize) {
+				*used = true;
+			}
+		};
+
+		for section in self.sections() {
+			match *section {
+				Section::Import(ref is) =>
+					for entry in is.entries() {
+						match *entry.external() {
+							External::Function(type_ref) => mark(type_ref),
+							#[cfg(feature = "exceptions")]
+							External::Tag(ref tag) => mark(tag.type_ref()),
+							_ => {},
+						}
+					},
+				Section::Function(ref fs) => fs.entries().iter().for_each(|f| mark(f.type_ref())),
+				#[cfg(feature = "exceptions")]
+				Section::Tag(ref ts) => ts.entries().iter().for_each(|tag| mark(tag.type_ref())),
+				Section::Code(ref cs) =>
+					for body in cs.bodies() {
+						let code = body.code().elements();
+						code.iter().filter_map(instruction_type_ref).for_each(&mut mark);
+					},
+				_ => {},
+			}
+		}
+
+		(0..types as u32).filter(|&index| !used[index as usize]).collect()
+	}
+
+	/// Remove the type section entries reported by
+	/// [`unused_type_indices`](Self::unused_type_indices) and shift down the references
+	/// to the following types.
+	///
+	/// Returns the number of removed types.
+	pub fn remove_unused_types(&mut self) -> usize {
+		let unused = self.unused_type_indices();
+		self.remove_types(&unused);
+		unused.len()
+	}
+
+	/// Merge structurally equal entries of the type section.
+	///
+	/// Every reference to a duplicate is redirected to the first equal type, then the
+	/// duplicates are removed and the references to the following types shifted down.
+	///
+	/// Returns the number of removed types.
+	pub fn dedup_types(&mut self) -> usize {
+		let types = self.type_section().map(|ts| ts.types()).unwrap_or(&[]);
+		let canonical: Vec<u32> = types
+			.iter()
+			.enumerate()
+			.map(|(index, ty)| types[..index].iter().position(|other| other == ty).unwrap_or(index))
+			.map(|index| index as u32)
+			.collect();
+		let duplicates: Vec<u32> = (0..canonical.len() as u32)
+			.filter(|&index| canonical[index as usize] != index)
+			.collect();
+		if duplicates.is_empty() {
+			return 0
+		}
+
+		self.map_type_indices(|type_ref| {
+			canonical.get(type_ref as usize).copied().unwrap_or(type_ref)
+		});
+		self.remove_types(&duplicates);
+		duplicates.len()
+	}
+
+	/// Remove the types at the sorted indices `removed` and shift down the references to
+	/// the following types.
+	fn remove_types(&mut self, removed: &[u32]) {
+		if removed.is_empty() {
+			return
+		}
+		if let Some(ts) = self.type_section_mut() {
+			let mut index = 0;
+			ts.types_mut().retain(|_| {
+				index += 1;
+				removed.binary_search(&(index - 1)).is_err()
+			});
+		}
+		self.map_type_indices(|type_ref| {
+			type_ref - removed.iter().take_while(|&&index| index < type_ref).count() as u32
+		});
+	}
+
+	/// Rewrite the type references of imports, functions, tags and instructions with `f`.
+	fn map_type_indices<F: Fn(u32) -> u32>(&mut self, f: F) {
+		let map = |type_ref: &mut u32| *type_ref = f(*type_ref);
+		for section in self.sections_mut() {
+			match *section {
+				Section::Import(ref mut is) =>
+					for entry in is.entries_mut() {
+						match *entry.external_mut() {
+							External::Function(ref mut type_ref) => map(type_ref),
+							#[cfg(feature = "exceptions")]
+							External::Tag(ref mut tag) => map(tag.type_ref_mut()),
+							_ => {},
+						}
+					},
+				Section::Function(ref mut fs) =>
+					fs.entries_mut().iter_mut().for_each(|f| map(f.type_ref_mut())),
+				#[cfg(feature = "exceptions")]
+				Section::Tag(ref mut ts) => ts.entries_mut().iter_mut().for_each(|tag| map(tag.type_ref_mut())),
+				Section::Code(ref mut cs) =>
+					for body in cs.bodies_mut() {
+						body.code_mut()
+							.elements_mut()
+							.iter_mut()
+							.filter_map(instruction_type_ref_mut)
+							.for_each(map);
+					},
+				_ => {},
+			}
+		}
+	}
+
+	fn is_function_referenced(&self, func_index: u32, defined_index: usize) -> bool {
+		let called =
+			self.code_section().map_or(false, |cs| {
+				cs.bodies().iter().enumerate().filter(|&(i, _)| i != defined_index).any(
+					|(_, body)| body.code().elements().contains(&Instruction::Call(func_index)),
+				)
+			});
+		let exported = self.export_section().map_or(false, |es| {
+			es.entries()
+				.iter()
+				.any(|entry| *entry.internal() == Internal::Function(func_index))
+		});
+		let in_elements = self.elements_section().map_or(false, |es| {
+			es.entries().iter().any(|segment| segment.members().contains(&func_index))
+		});
+		called || exported || in_elements || self.start_section() == Some(func_index)
+	}
+
+	/// Names of exports which refer to imported entries, i.e. re-export an import.
+	///
+	/// This is legal, but might be unintended.
+	pub fn reexported_imports(&self) -> Vec<String> {
+		self.export_section()
+			.map(|es| {
+				es.entries()
+					.iter()
+					.filter(|entry| {
+						let (count_type, index) = match *entry.internal() {
+							Internal::Function(index) => (ImportCountType::Function, index),
+							Internal::Table(index) => (ImportCountType::Table, index),
+							Internal::Memory(index) => (ImportCountType::Memory, index),
+							Internal::Global(index) => (ImportCountType::Global, index),
+							#[cfg(feature = "exceptions")]
+							Internal::Tag(index) => (ImportCountType::Tag, index),
+						};
+						(index as usize) < self.import_count(count_type)
+					})
+					.map(|entry| entry.field().to_owned())
+					.collect()
+			})
+			.unwrap_or_default()
+	}
+
+	/// Size in bytes of the function bodies, as encoded in the code section.
+	///
+	/// Each body is counted together with its size prefix.
+	pub fn code_size(&self) -> Result<usize, Error> {
+		let mut writer = io::CountingWriter::new();
+		for body in self.code_section().map(|cs| cs.bodies()).unwrap_or(&[]) {
+			body.serialize(&mut writer)?;
+		}
+		Ok(writer.count())
+	}
+
+	/// Total number of instructions in the function bodies, including their final `end`.
+	pub fn instruction_count(&self) -> usize {
+		let bodies = self.code_section().map(|cs| cs.bodies()).unwrap_or(&[]);
+		bodies.iter().map(|body| body.code().elements().len()).sum()
+	}
+
+	/// Iterator over the constant expressions of the module: global initializers first,
+	/// then the offsets of active element segments and of active data segments.
+	pub fn init_exprs(&self) -> impl Iterator<Item = &InitExpr> {
+		let globals = self.global_section().map(|gs| gs.entries()).unwrap_or(&[]);
+		let elements = self.elements_section().map(|es| es.entries()).unwrap_or(&[]);
+		let data = self.data_section().map(|ds| ds.entries()).unwrap_or(&[]);
+		globals
+			.iter()
+			.map(|entry| entry.init_expr())
+			.chain(elements.iter().filter_map(|segment| segment.offset().as_ref()))
+			.chain(data.iter().filter_map(|segment| segment.offset().as_ref()))
+	}
+
+	/// Query functions space.
+	pub fn functions_space(&self) -> usize {
+		self.import_count(ImportCountType::Function) +
+			self.function_section().map(|fs| fs.entries().len()).unwrap_or(0)
+	}
+
+	/// Query globals space.
+	pub fn globals_space(&self) -> usize {
+		self.import_count(ImportCountType::Global) +
+			self.global_section().map(|gs| gs.entries().len()).unwrap_or(0)
+	}
+
+	/// Query table space.
+	pub fn table_space(&self) -> usize {
+		self.import_count(ImportCountType::Table) +
+			self.table_section().map(|ts| ts.entries().len()).unwrap_or(0)
+	}
+
+	/// Query memory space.
+	pub fn memory_space(&self) -> usize {
+		self.import_count(ImportCountType::Memory) +
+			self.memory_section().map(|ms| ms.entries().len()).unwrap_or(0)
+	}
+
+	/// Number of element segments, zero if there is no element section.
+	pub fn element_segment_count(&self) -> usize {
+		self.elements_section().map(|es| es.entries().len()).unwrap_or(0)
+	}
+
+	/// Number of data segments, zero if there is no data section.
+	pub fn data_segment_count(&self) -> usize {
+		self.data_section().map(|ds| ds.entries().len()).unwrap_or(0)
+	}
+
+	/// Initial value of the global at `index` in the globals space, if it is known statically.
+	///
+	/// Returns `None` for imported globals, out of range indices and initializers that
+	/// are not constant. `get_global` initializers are followed when they reference
+	/// a preceding global which itself has a constant value.
+	pub fn global_init_value(&self, index: u32) -> Option<ConstValue> {
+		let imported_globals = self.import_count(ImportCountType::Global);
+		let defined_index = (index as usize).checked_sub(imported_globals)?;
+		let entry = self.global_section()?.entries().get(defined_index)?;
+		match entry.init_expr().code() {
+			[Instruction::GetGlobal(referenced), Instruction::End] if *referenced < index =>
+				self.global_init_value(*referenced),
+			_ => entry.init_expr().eval(),
+		}
+	}
+
+	/// Check that the start function, if any, exists and has the signature `[] -> []`.
+	///
+	/// This is a cheap check which doesn't validate anything else in the module.
+	pub fn validate_start(&self) -> Result<(), Error> {
+		let start = match self.start_section() {
+			Some(start) => start,
+			None => return Ok(()),
+		};
+
+		let type_ref = self.function_type_refs().nth(start as usize).ok_or(
+			Error::FunctionIndexOutOfBounds { index: start, len: self.functions_space() as u32 },
+		)?;
+
+		let types = self.type_section().map(|ts| ts.types()).unwrap_or(&[]);
+		match types.get(type_ref as usize) {
+			Some(Type::Function(func_type))
+				if func_type.params().is_empty() && func_type.results().is_empty() =>
+				Ok(()),
+			Some(_) => Err(Error::Other("Start function should have type [] -> []")),
+			None => Err(Error::TypeIndexOutOfBounds { index: type_ref, len: types.len() as u32 }),
+		}
+	}
+
+	/// Append `func_type` to the type section, creating it if needed, and return its index.
+	///
+	/// Identical types already in the section are not reused.
+	pub fn append_type(&mut self, func_type: FunctionType) -> u32 {
+		if self.type_section().is_none() {
+			self.insert_section(Section::Type(TypeSection::default()))
+				.expect("type section does not exist; qed");
+		}
+		self.type_section_mut()
+			.expect("type section exists; qed")
+			.push(Type::Function(func_type))
+	}
+
+	/// Signature of the function at `func_index` in the functions space.
+	///
+	/// Imported functions occupy the low indices, followed by the functions defined
+	/// in the function section. Returns `None` if the function or its type does not exist.
+	pub fn function_type_of(&self, func_index: u32) -> Option<&FunctionType> {
+		let type_ref = self.function_type_refs().nth(func_index as usize)?;
+		match self.type_section()?.types().get(type_ref as usize)? {
+			Type::Function(func_type) => Some(func_type),
+		}
+	}
+
+	/// Number of functions (imported and defined) using each signature.
+	///
+	/// Functions referring to a missing type are not counted.
+	pub fn signature_histogram(&self) -> BTreeMap<FunctionType, usize> {
+		let types = self.type_section().map(|ts| ts.types()).unwrap_or(&[]);
+		let mut histogram = BTreeMap::new();
+		for type_ref in self.function_type_refs() {
+			if let Some(Type::Function(func_type)) = types.get(type_ref as usize) {
+				*histogram.entry(func_type.clone()).or_insert(0) += 1;
+			}
+		}
+		histogram
+	}
+
+	/// Type references of all functions in the functions space, imported ones first.
+	fn function_type_refs(&self) -> impl Iterator<Item = u32> + '_ {
+		let imported_functions = self.import_section().map(|is| {
+			is.entries().iter().filter_map(|entry| match *entry.external() {
+				External::Function(type_ref) => Some(type_ref),
+				_ => None,
+			})
+		});
+		let defined_functions = self
+			.function_section()
+			.map(|fs| fs.entries().iter().map(|func| func.type_ref()));
+		imported_functions
+			.into_iter()
+			.flatten()
+			.chain(defined_functions.into_iter().flatten())
+	}
+
+	/// Apply `visitor` to every instruction of the module.
+	///
+	/// Covers all function bodies, global initializers and offset expressions
+	/// of data and element segments.
+	pub fn visit_instructions<V: InstructionVisitor>(&mut self, visitor: &mut V) {
+		for section in self.sections_mut() {
+			let code: Vec<&mut Vec<Instruction>> = match *section {
+				Section::Code(ref mut cs) =>
+					cs.bodies_mut().iter_mut().map(|body| body.code_mut().elements_mut()).collect(),
+				Section::Global(ref mut gs) => gs
+					.entries_mut()
+					.iter_mut()
+					.map(|global| global.init_expr_mut().code_mut())
+					.collect(),
+				Section::Data(ref mut ds) => ds
+					.entries_mut()
+					.iter_mut()
+					.filter_map(|segment| segment.offset_mut().as_mut())
+					.map(|offset| offset.code_mut())
+					.collect(),
+				Section::Element(ref mut es) => es
+					.entries_mut()
+					.iter_mut()
+					.filter_map(|segment| segment.offset_mut().as_mut())
+					.map(|offset| offset.code_mut())
+					.collect(),
+				_ => continue,
+			};
+			for instr in code.into_iter().flatten() {
+				visitor.visit(instr);
+			}
+		}
+	}
+
+	/// Maximum block nesting depth over all function bodies of the module.
+	///
+	/// See `FuncBody::max_block_depth`.
+	pub fn max_block_depth(&self) -> usize {
+		self.code_section()
+			.and_then(|cs| cs.bodies().iter().map(|body| body.max_block_depth()).max())
+			.unwrap_or(0)
+	}
+
+	/// Check that offsets of active data and element segments only read globals which are
+	/// already initialized when the segments are applied.
+	///
+	/// Segments are initialized before any global defined by the module is available, so
+	/// a `get_global` in an offset expression must reference an imported immutable global.
+	pub fn check_init_order(&self) -> Result<(), Error> {
+		let imported_globals: Vec<bool> = self
+			.import_section()
+			.map(|is| {
+				is.entries()
+					.iter()
+					.filter_map(|entry| match *entry.external() {
+						External::Global(ref global_type) => Some(global_type.is_mutable()),
+						_ => None,
+					})
+					.collect()
+			})
+			.unwrap_or_default();
+
+		let data_offsets = self
+			.data_section()
+			.map(|ds| ds.entries())
+			.unwrap_or(&[])
+			.iter()
+			.filter_map(|segment| segment.offset().as_ref());
+		let element_offsets = self
+			.elements_section()
+			.map(|es| es.entries())
+			.unwrap_or(&[])
+			.iter()
+			.filter_map(|segment| segment.offset().as_ref());
+
+		for offset in data_offsets.chain(element_offsets) {
+			for instruction in offset.code() {
+				if let Instruction::GetGlobal(index) = *instruction {
+					match imported_globals.get(index as usize) {
+						Some(false) => {},
+						Some(true) =>
+							return Err(Error::Other("Segment offset references a mutable global")),
+						None if (index as usize) < self.globals_space() =>
+							return Err(Error::Other(
+								"Segment offset references a non-imported global",
+							)),
+						None =>
+							return Err(Error::GlobalIndexOutOfBounds {
+								index,
+								len: self.globals_space() as u32,
+							}),
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl Deserialize for Module {
+	type Error = super::Error;
+
+	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
+		Module::deserialize_with_config(reader, ParseConfig::default())
+	}
+}
+
+impl Module {
+	/// Deserialize a module, keeping instructions unknown to this crate in function bodies
+	/// as `Instruction::Unknown`.
+	///
+	/// See `Instruction::deserialize_tolerant` for how `unknown_opcode_hook` is used.
+	pub fn deserialize_tolerant<R: io::Read>(
+		reader: &mut R,
+		unknown_opcode_hook: UnknownOpcodeHook,
+	) -> Result<Self, Error> {
+		let config = ParseConfig {
+			unknown_opcode_hook: Some(unknown_opcode_hook),
+			..ParseConfig::default()
+		};
+		Module::deserialize_with_config(reader, config)
+	}
+
+	/// Deserialize a module with the given parsing options.
+	pub fn deserialize_with_config<R: io::Read>(
+		reader: &mut R,
+		config: ParseConfig,
+	) -> Result<Self, Error> {
+		let mut sections = Vec::new();
+
+		let mut magic = [0u8; 4];
+		reader.read(&mut magic)?;
+		if magic != WASM_MAGIC_NUMBER {
+			return Err(Error::InvalidMagic)
+		}
+
+		let version: u32 = Uint32::deserialize(reader)?.into();
+
+		if version != 1 {
+			return Err(Error::UnsupportedVersion(version))
+		}
+
+		let mut last_section_order = 0;
+
+		loop {
+			match Section::deserialize_with(reader, config) {
+				Err(Error::UnexpectedEof) => break,
+				Err(e) => return Err(e.at(reader.offset())),
+				Ok(section) => {
+					check_section_order(&mut last_section_order, &section)
+						.map_err(|e| e.at(reader.offset()))?;
+					sections.push(section);
+				},
+			}
+		}
+
+		let module = Module { magic: u32::from_le_bytes(magic), version, sections };
+
+		if module.code_section().map(|cs| cs.bodies().len()).unwrap_or(0) !=
+			module.function_section().map(|fs| fs.entries().len()).unwrap_or(0)
+		{
+			return Err(Error::InconsistentCode)
+		}
+
+		if let Some(data_count) = module.data_count_section() {
+			if data_count as usize !=
+				module.data_section().map(|ds| ds.entries().len()).unwrap_or(0)
+			{
+				return Err(Error::InconsistentDataCount)
+			}
+		}
+
+		Ok(module)
+	}
+}
+
+/// Local declarations with empty ones dropped and adjacent ones of the same type merged.
+fn merged_locals(locals: &[Local]) -> Vec<(u64, ValueType)> {
+	let mut merged: Vec<(u64, ValueType)> = Vec::new();
+	for local in locals.iter().filter(|local| local.count() > 0) {
+		match merged.last_mut() {
+			Some(last) if last.1 == local.value_type() => last.0 += u64::from(local.count()),
+			_ => merged.push((u64::from(local.count()), local.value_type())),
+		}
+	}
+	merged
+}
+
+/// Type index referenced by `instruction`, if any.
+fn instruction_type_ref(instruction: &Instruction) -> Option<u32> {
+	match *instruction {
+		Instruction::CallIndirect(type_ref, _) => Some(type_ref),
+		#[cfg(feature = "multi_value")]
+		Instruction::Block(BlockType::TypeIndex(type_ref)) |
+		Instruction::Loop(BlockType::TypeIndex(type_ref)) |
+		Instruction::If(BlockType::TypeIndex(type_ref)) => Some(type_ref),
+		#[cfg(all(feature = "multi_value", feature = "exceptions"))]
+		Instruction::Exception(ExceptionInstruction::Try(BlockType::TypeIndex(type_ref))) =>
+			Some(type_ref),
+		_ => None,
+	}
+}
+
+/// Type index referenced by `instruction`, if any (mutable).
+fn instruction_type_ref_mut(instruction: &mut Instruction) -> Option<&mut u32> {
+	match *instruction {
+		Instruction::CallIndirect(ref mut type_ref, _) => Some(type_ref),
+		#[cfg(feature = "multi_value")]
+		Instruction::Block(BlockType::TypeIndex(ref mut type_ref)) |
+		Instruction::Loop(BlockType::TypeIndex(ref mut type_ref)) |
+		Instruction::If(BlockType::TypeIndex(ref mut type_ref)) => Some(type_ref),
+		#[cfg(all(feature = "multi_value", feature = "exceptions"))]
+		Instruction::Exception(ExceptionInstruction::Try(BlockType::TypeIndex(
+			ref mut type_ref,
+		))) => Some(type_ref),
+		_ => None,
+	}
+}
+
+/// Check that `section` may follow sections up to `last_section_order`, and advance it.
+fn check_section_order(last_section_order: &mut u8, section: &Section) -> Result<(), Error> {
+	if section.order() != 0 {
+		match *last_section_order {
+			x if x > section.order() => return Err(Error::SectionsOutOfOrder),
+			x if x == section.order() => return Err(Error::DuplicatedSections(x)),
+			_ => {},
+		};
+
+		*last_section_order = section.order();
+	}
+	Ok(())
+}
+
+impl fmt::Display for Module {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for section in self.sections() {
+			match *section {
+				Section::Unparsed { id, ref payload } =>
+					writeln!(f, "unparsed:{} ({} bytes)", id, payload.len())?,
+				Section::Custom(ref custom) => writeln!(f, "custom:{}", custom.name())?,
+				Section::Type(ref ts) => writeln!(f, "type[{}]", ts.types().len())?,
+				Section::Import(ref is) => writeln!(f, "import[{}]", is.entries().len())?,
+				Section::Function(ref fs) => writeln!(f, "function[{}]", fs.entries().len())?,
+				Section::Table(ref ts) => writeln!(f, "table[{}]", ts.entries().len())?,
+				Section::Memory(ref ms) => writeln!(f, "memory[{}]", ms.entries().len())?,
+				#[cfg(feature = "exceptions")]
+				Section::Tag(ref ts) => writeln!(f, "tag[{}]", ts.entries().len())?,
+				Section::Global(ref gs) => writeln!(f, "global[{}]", gs.entries().len())?,
+				Section::Export(ref es) => writeln!(f, "export[{}]", es.entries().len())?,
+				Section::Start(index) => writeln!(f, "start {}", index)?,
+				Section::Element(ref es) => writeln!(f, "element[{}]", es.entries().len())?,
+				Section::DataCount(count) => writeln!(f, "datacount {}", count)?,
+				Section::Code(ref cs) => writeln!(f, "code[{}]", cs.bodies().len())?,
+				Section::Data(ref ds) => writeln!(f, "data[{}]", ds.entries().len())?,
+				Section::Name(_) => writeln!(f, "custom:name")?,
+				Section::Reloc(ref rs) => writeln!(f, "custom:{}", rs.name())?,
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Serialize for Module {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(mut self, w: &mut W) -> Result<(), Self::Error> {
+		self.order_sections();
+		self.serialize_unordered(w)
+	}
+}
+
+impl Module {
+	/// Serialize the module with its sections in their current order.
+	///
+	/// Unlike the [`Serialize`] implementation, this does not call [`Module::order_sections`]
+	/// first, so the sections are written exactly as they are laid out in the module.
+	pub fn serialize_unordered<W: io::Write>(self, w: &mut W) -> Result<(), Error> {
+		Uint32::from(self.magic).serialize(w)?;
+		Uint32::from(self.version).serialize(w)?;
+		for section in self.sections.into_iter() {
+			// todo: according to the spec the name section should appear after the data section
+			section.serialize(w)?;
+		}
+		Ok(())
+	}
+
+	/// Serialize the module without buffering the code and data sections.
+	///
+	/// See [`Section::serialize_streamed`].
+	pub fn serialize_streamed<W: io::Write>(mut self, w: &mut W) -> Result<(), Error> {
+		self.order_sections();
+		Uint32::from(self.magic).serialize(w)?;
+		Uint32::from(self.version).serialize(w)?;
+		for section in self.sections.into_iter() {
+			section.serialize_streamed(w)?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct PeekSection<'a> {
+	cursor: usize,
+	region: &'a [u8],
+}
+
+impl<'a> io::Read for PeekSection<'a> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+		let available = cmp::min(buf.len(), self.region.len() - self.cursor);
+		if available < buf.len() {
+			return Err(io::Error::UnexpectedEof)
+		}
+
+		let range = self.cursor..self.cursor + buf.len();
+		buf.copy_from_slice(&self.region[range]);
+
+		self.cursor += available;
+		Ok(())
+	}
+}
+
+/// Whether the provided bytes start with the wasm magic number and version 1.
+///
+/// Only the 8-byte preamble is checked, nothing is parsed.
+pub fn is_wasm(source: &[u8]) -> bool {
+	source.len() >= 8 && source[..4] == WASM_MAGIC_NUMBER && source[4..8] == [1, 0, 0, 0]
+}
+
+/// Returns size of the module in the provided stream.
+pub fn peek_size(source: &[u8]) -> usize {
+	if source.len() < 9 {
+		return 0
+	}
+
+	let mut cursor = 8;
+	loop {
+		let (new_cursor, section_id, section_len) = {
+			let mut peek_section = PeekSection { cursor: 0, region: &source[cursor..] };
+			let section_id: u8 = match super::VarUint7::deserialize(&mut peek_section) {
+				Ok(res) => res.into(),
+				Err(_) => break,
+			};
+			let section_len: u32 = match super::VarUint32::deserialize(&mut peek_section) {
+				Ok(res) => res.into(),
+				Err(_) => break,
+			};
+
+			(peek_section.cursor, section_id, section_len)
+		};
+
+		let known_id = section_id <= 12 || (cfg!(feature = "exceptions") && section_id == 13);
+		if known_id && section_len > 0 {
+			let next_cursor = cursor + new_cursor + section_len as usize;
+
+			match next_cursor {
+				x if x > source.len() => break,
+				x if x == source.len() => {
+					cursor = next_cursor;
+					break
+				},
+				_ => {},
+			}
+			cursor = next_cursor;
+		} else {
+			break
+		}
+	}
+
+	cursor
+}
+
+/// Ids and payload lengths of the top-level sections of the module, in order.
+///
+/// Only the section headers are read, payloads are skipped without being parsed.
+pub fn peek_sections(source: &[u8]) -> Result<Vec<(u8, usize)>, Error> {
+	let mut reader = io::Cursor::new(source);
+	let mut magic = [0u8; 4];
+	io::Read::read(&mut reader, &mut magic)?;
+	if magic != WASM_MAGIC_NUMBER {
+		return Err(Error::InvalidMagic)
+	}
+	let version: u32 = Uint32::deserialize(&mut reader)?.into();
+	if version != 1 {
+		return Err(Error::UnsupportedVersion(version))
+	}
+
+	let mut sections = Vec::new();
+	let mut cursor = reader.position();
+	while cursor < source.len() {
+		let mut header = io::Cursor::new(&source[cursor..]);
+		let id: u8 = super::VarUint7::deserialize(&mut header)?.into();
+		let len: usize = super::VarUint32::deserialize(&mut header)?.into();
+		let payload_start = cursor + header.position();
+		if source.len() - payload_start < len {
+			return Err(Error::TruncatedInput.at(Some(payload_start)))
+		}
+		sections.push((id, len));
+		cursor = payload_start + len;
+	}
+	Ok(sections)
+}
+
+#[cfg(test)]
 mod integration_tests {
 	use super::{
 		super::{
-			deserialize_buffer, deserialize_file, serialize, CodeSection, ExportSection,
+			deserialize_buffer, deserialize_file, serialize, CodeSection, Error, ExportSection,
 			FunctionSection, Section, TypeSection,
 		},
 		Module,
 	};
 
 	#[test]
-	fn hello() {
-		let module = deserialize_file("./res/cases/v1/hello.wasm").expect("Should be deserialized");
+	fn hello() {
+		let module = deserialize_file("./res/cases/v1/hello.wasm").expect("Should be deserialized");
+
+		assert_eq!(module.version(), 1);
+		assert_eq!(module.sections().len(), 8);
+	}
+
+	#[test]
+	fn reexported_imports() {
+		use super::super::{ExportEntry, Internal};
+		use crate::builder;
+
+		let module = builder::module()
+			.import()
+			.module("env")
+			.field("log")
+			.external()
+			.func(0)
+			.build()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.build()
+			.build()
+			.with_export(ExportEntry::new("log".into(), Internal::Function(0)))
+			.with_export(ExportEntry::new("main".into(), Internal::Function(1)))
+			.build();
+
+		assert_eq!(module.reexported_imports(), vec!["log".to_owned()]);
+	}
+
+	#[test]
+	fn signature_histogram() {
+		use super::super::{FunctionType, ValueType};
+		use crate::builder;
+
+		let mut module = builder::module();
+		for _ in 0..3 {
+			module = module.function().signature().with_param(ValueType::I32).build().build();
+		}
+		let module = module
+			.function()
+			.signature()
+			.with_result(ValueType::I64)
+			.build()
+			.build()
+			.build();
+
+		let histogram = module.signature_histogram();
+		assert_eq!(histogram.len(), 2);
+		assert_eq!(histogram[&FunctionType::new(vec![ValueType::I32], vec![])], 3);
+		assert_eq!(histogram[&FunctionType::new(vec![], vec![ValueType::I64])], 1);
+	}
+
+	#[test]
+	fn visit_instructions() {
+		use super::{
+			super::{Instruction, Instructions},
+			InstructionVisitor,
+		};
+		use crate::builder;
+
+		struct ShiftCalls;
+
+		impl InstructionVisitor for ShiftCalls {
+			fn visit(&mut self, instr: &mut Instruction) {
+				if let Instruction::Call(ref mut idx) = *instr {
+					*idx += 1;
+				}
+			}
+		}
+
+		let mut module = builder::module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.with_instructions(Instructions::new(vec![
+				Instruction::Call(0),
+				Instruction::Call(3),
+				Instruction::End,
+			]))
+			.build()
+			.build()
+			.build();
+		module.visit_instructions(&mut ShiftCalls);
+
+		assert_eq!(
+			module.code_section().unwrap().bodies()[0].code().elements(),
+			&[Instruction::Call(1), Instruction::Call(4), Instruction::End]
+		);
+	}
+
+	#[test]
+	fn from_sections() {
+		let module = deserialize_file("./res/cases/v1/test5.wasm").expect("Should be deserialized");
+		let bytes = serialize(module.clone()).expect("Should be serialized");
+
+		let module = Module::from_sections(module.into_sections()).expect("Should be recomposed");
+		assert_eq!(serialize(module).expect("Should be serialized"), bytes);
+
+		let duplicated =
+			vec![Section::Type(TypeSection::default()), Section::Type(TypeSection::default())];
+		assert!(matches!(Module::from_sections(duplicated), Err(Error::DuplicatedSections(_))));
+
+		let reversed = vec![
+			Section::Function(FunctionSection::default()),
+			Section::Type(TypeSection::default()),
+		];
+		assert!(matches!(Module::from_sections(reversed), Err(Error::SectionsOutOfOrder)));
+	}
+
+	#[test]
+	fn export_memory_as_import() {
+		use super::super::External;
+		use crate::builder;
+
+		let original = builder::module()
+			.memory()
+			.with_min(1)
+			.with_max(Some(2))
+			.with_data(0, vec![0x2a])
+			.build()
+			.build();
+
+		let mut module = original.clone();
+		module.export_memory_as_import("env", "memory").expect("Should be externalized");
+		assert!(module.memory_section().is_none());
+		let import = &module.import_section().expect("Should have import section").entries()[0];
+		assert_eq!((import.module(), import.field()), ("env", "memory"));
+		assert!(matches!(*import.external(), External::Memory(_)));
+		assert!(module.export_memory_as_import("env", "memory").is_err());
+
+		let bytes = serialize(module).expect("Should be serialized");
+		let mut module = deserialize_buffer::<Module>(&bytes).expect("Should be deserialized");
+		module.internalize_memory().expect("Should be internalized");
+		assert_eq!(module, original);
+		assert!(module.internalize_memory().is_err());
+	}
+
+	#[test]
+	fn strip_initializers() {
+		use super::super::{DataSection, DataSegment, ElementSection, ElementSegment};
+
+		let mut module = Module::new(vec![
+			Section::Type(TypeSection::default()),
+			Section::Element(ElementSection::with_entries(vec![ElementSegment::new(
+				0,
+				None,
+				vec![],
+			)])),
+			Section::DataCount(1),
+			Section::Data(DataSection::with_entries(vec![DataSegment::new(0, None, vec![0x2a])])),
+		]);
+		module.strip_initializers().expect("Should be stripped");
+
+		assert_eq!(module.sections().len(), 1);
+		assert!(module.type_section().is_some());
+	}
+
+	#[cfg(feature = "bulk")]
+	#[test]
+	fn strip_initializers_used_by_code() {
+		use super::super::{BulkInstruction, DataSection, DataSegment, Instruction, Instructions};
+		use crate::builder;
+
+		let mut module = builder::module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.with_instructions(Instructions::new(vec![
+				Instruction::Bulk(BulkInstruction::MemoryDrop(0)),
+				Instruction::End,
+			]))
+			.build()
+			.build()
+			.build();
+		let passive = DataSegment::new(0, None, vec![0x2a]);
+		module
+			.insert_section(Section::Data(DataSection::with_entries(vec![passive])))
+			.expect("Should be inserted");
+
+		assert!(module.strip_initializers().is_err());
+		assert!(module.data_section().is_some());
+	}
+
+	#[test]
+	fn remove_function() {
+		use super::super::{ExportEntry, Instruction, Instructions, Internal};
+		use crate::builder;
+
+		let mut module = builder::module()
+			.import()
+			.module("env")
+			.field("log")
+			.external()
+			.func(0)
+			.build()
+			.with_export(ExportEntry::new("main".into(), Internal::Function(3)));
+		for instructions in [
+			vec![Instruction::Call(0), Instruction::Call(3), Instruction::End],
+			vec![Instruction::Call(2), Instruction::End],
+			vec![Instruction::Call(0), Instruction::End],
+		] {
+			module = module
+				.function()
+				.signature()
+				.build()
+				.body()
+				.with_instructions(Instructions::new(instructions))
+				.build()
+				.build();
+		}
+		let mut module = module.build();
+
+		assert!(module.remove_function(0).is_err());
+		assert!(module.remove_function(3).is_err());
+		assert!(module.remove_function(4).is_err());
+
+		module.remove_function(2).expect("Should be removed");
+		let bodies = module.code_section().expect("Should have code section").bodies();
+		assert_eq!(bodies.len(), 2);
+		assert_eq!(
+			bodies[0].code().elements(),
+			&[Instruction::Call(0), Instruction::Call(2), Instruction::End]
+		);
+		assert_eq!(bodies[1].code().elements(), &[Instruction::Call(0), Instruction::End]);
+		assert_eq!(
+			module.export_section().expect("Should have export section").entries()[0].internal(),
+			&Internal::Function(2)
+		);
+	}
+
+	#[test]
+	fn deduplicate_functions() {
+		use super::super::{ExportEntry, Instruction, Instructions, Internal};
+		use crate::builder;
+
+		let mut module =
+			builder::module().with_export(ExportEntry::new("dup".into(), Internal::Function(2)));
+		for instructions in [
+			vec![Instruction::Nop, Instruction::End],
+			vec![Instruction::Call(0), Instruction::Call(2), Instruction::End],
+			vec![Instruction::Nop, Instruction::End],
+			vec![Instruction::Call(2), Instruction::End],
+		] {
+			module = module
+				.function()
+				.signature()
+				.build()
+				.body()
+				.with_instructions(Instructions::new(instructions))
+				.build()
+				.build();
+		}
+		let mut module = module.build();
+
+		assert_eq!(module.deduplicate_functions(), 1);
+		let functions = module.function_section().expect("Should have function section");
+		assert_eq!(functions.entries().len(), 3);
+		let bodies = module.code_section().expect("Should have code section").bodies();
+		assert_eq!(bodies.len(), 3);
+		assert_eq!(
+			bodies[1].code().elements(),
+			&[Instruction::Call(0), Instruction::Call(0), Instruction::End]
+		);
+		assert_eq!(bodies[2].code().elements(), &[Instruction::Call(0), Instruction::End]);
+		assert_eq!(
+			module.export_section().expect("Should have export section").entries()[0].internal(),
+			&Internal::Function(0)
+		);
+		assert_eq!(module.deduplicate_functions(), 0);
+	}
+
+	#[test]
+	fn function_names_follow_indices() {
+		use super::super::{IndexMap, LocalNameSubsection};
+
+		let mut module = deserialize_file("./res/cases/v1/with_names.wasm")
+			.expect("Should be deserialized")
+			.parse_names()
+			.expect("Names to be parsed");
+		let mut locals = LocalNameSubsection::default();
+		let local_names: IndexMap<_> = vec![(0, "removed".to_owned())].into_iter().collect();
+		locals.local_names_mut().insert(11, local_names);
+		let local_names: IndexMap<_> = vec![(0, "kept".to_owned())].into_iter().collect();
+		locals.local_names_mut().insert(12, local_names);
+		*module.names_section_mut().expect("Should have name section").locals_mut() = Some(locals);
+		let names =
+			|module: &Module| module.names_section().expect("Should have name section").clone();
+		let before = names(&module);
+		let function_names = before.functions().expect("Should have function names").names();
+
+		let mut removed = module.clone();
+		removed.remove_function(11).expect("Should be removed");
+		let after = names(&removed);
+		let names_after = after.functions().expect("Should have function names").names();
+		assert_eq!(names_after.len(), function_names.len() - 1);
+		assert_eq!(names_after.get(10), function_names.get(10));
+		assert_eq!(names_after.get(11), function_names.get(12));
+		let local_names = after.locals().expect("Should have local names").local_names();
+		assert_eq!(local_names.len(), 1);
+		assert_eq!(
+			local_names.get(11).and_then(|names| names.get(0)).map(String::as_str),
+			Some("kept")
+		);
+
+		let removed = module.deduplicate_functions();
+		assert!(removed > 0);
+		let after = names(&module);
+		let names_after = after.functions().expect("Should have function names").names();
+		assert_eq!(names_after.len(), function_names.len() - removed);
+		assert_eq!(names_after.len(), module.functions_space());
+	}
+
+	#[test]
+	fn append_type() {
+		use super::super::{Func, FuncBody, FunctionType, Instruction, Instructions, ValueType};
+
+		let mut module = Module::default();
+		let func_type = FunctionType::new(vec![ValueType::I32], vec![]);
+		assert_eq!(module.append_type(func_type.clone()), 0);
+		assert_eq!(module.append_type(func_type.clone()), 1);
+
+		let type_ref = module.append_type(FunctionType::new(vec![], vec![ValueType::I64]));
+		assert_eq!(type_ref, 2);
+		module
+			.insert_section(Section::Function(FunctionSection::with_entries(vec![Func::new(
+				type_ref,
+			)])))
+			.expect("Should be inserted");
+		module
+			.insert_section(Section::Code(CodeSection::with_bodies(vec![FuncBody::new(
+				vec![],
+				Instructions::new(vec![Instruction::I64Const(0), Instruction::End]),
+			)])))
+			.expect("Should be inserted");
+
+		let bytes = serialize(module).expect("Should be serialized");
+		let module = deserialize_buffer::<Module>(&bytes).expect("Should be deserialized");
+		assert_eq!(
+			module.function_type_of(0),
+			Some(&FunctionType::new(vec![], vec![ValueType::I64]))
+		);
+		assert_eq!(module.type_section().expect("Should have type section").types().len(), 3);
+	}
+
+	#[test]
+	fn import_count() {
+		use super::{
+			super::{
+				External, GlobalType, ImportEntry, ImportSection, MemoryType, TableType, ValueType,
+			},
+			ImportCountType,
+		};
+
+		assert_eq!(Module::default().import_count(ImportCountType::Function), 0);
+
+		let import = |field: &str, external| ImportEntry::new("env".into(), field.into(), external);
+		let module = Module::new(vec![Section::Import(ImportSection::with_entries(vec![
+			import("f0", External::Function(0)),
+			import("memory", External::Memory(MemoryType::new(1, None))),
+			import("g0", External::Global(GlobalType::new(ValueType::I32, false))),
+			import("f1", External::Function(0)),
+			import("g1", External::Global(GlobalType::new(ValueType::I64, true))),
+			import("f2", External::Function(0)),
+		]))]);
 
+		assert_eq!(module.import_count(ImportCountType::Function), 3);
+		assert_eq!(module.import_count(ImportCountType::Global), 2);
+		assert_eq!(module.import_count(ImportCountType::Memory), 1);
+		assert_eq!(module.import_count(ImportCountType::Table), 0);
+
+		let module = Module::new(vec![Section::Import(ImportSection::with_entries(vec![import(
+			"table",
+			External::Table(TableType::new(1, None)),
+		)]))]);
+		assert_eq!(module.import_count(ImportCountType::Table), 1);
+	}
+
+	#[test]
+	fn function_type_of() {
+		use super::super::{
+			External, Func, FunctionType, ImportEntry, ImportSection, Type, ValueType,
+		};
+
+		let types = vec![
+			Type::Function(FunctionType::new(vec![ValueType::I32], vec![])),
+			Type::Function(FunctionType::new(vec![], vec![ValueType::I64])),
+		];
+		let module = Module::new(vec![
+			Section::Type(TypeSection::with_types(types)),
+			Section::Import(ImportSection::with_entries(vec![ImportEntry::new(
+				"env".into(),
+				"log".into(),
+				External::Function(0),
+			)])),
+			Section::Function(FunctionSection::with_entries(vec![Func::new(1), Func::new(0)])),
+		]);
+
+		let log = FunctionType::new(vec![ValueType::I32], vec![]);
+		let get = FunctionType::new(vec![], vec![ValueType::I64]);
+		assert_eq!(module.function_type_of(0), Some(&log));
+		assert_eq!(module.function_type_of(1), Some(&get));
+		assert_eq!(module.function_type_of(2), Some(&log));
+		assert_eq!(module.function_type_of(3), None);
+	}
+
+	#[test]
+	fn display() {
+		use super::super::CustomSection;
+		use crate::builder;
+
+		let module = builder::module()
+			.function()
+			.main()
+			.signature()
+			.build()
+			.body()
+			.build()
+			.build()
+			.with_section(Section::Custom(CustomSection::new("producers".into(), vec![])))
+			.build();
+
+		assert_eq!(
+			format!("{}", module),
+			"type[1]\nfunction[1]\nstart 0\ncode[1]\ncustom:producers\n"
+		);
+	}
+
+	#[test]
+	fn set_version() {
+		let mut module = Module::default();
 		assert_eq!(module.version(), 1);
-		assert_eq!(module.sections().len(), 8);
+
+		module.set_version(0x0d);
+		assert_eq!(module.version(), 0x0d);
+		let bytes = module.into_bytes().expect("Failed to serialize");
+		assert_eq!(&bytes[4..8], &[0x0d, 0x00, 0x00, 0x00]);
+		assert!(Module::from_bytes(bytes).is_err());
 	}
 
 	#[test]
@@ -831,6 +2321,38 @@ mod integration_tests {
 		assert_eq!(I64Store(0, 32), func.code().elements()[2]);
 	}
 
+	#[test]
+	fn is_wasm() {
+		use super::is_wasm;
+
+		let buf = serialize(Module::default()).expect("serialization to succeed");
+		assert!(is_wasm(&buf));
+		assert!(is_wasm(&[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0xff]));
+		assert!(!is_wasm(&buf[..7]));
+		assert!(!is_wasm(&[0x00, 0x61, 0x73, 0x6d, 0x02, 0x00, 0x00, 0x00]));
+		assert!(!is_wasm(b"\x7fELF\x02\x01\x01\x00"));
+	}
+
+	#[test]
+	fn peek_sections() {
+		use super::{super::VarUint32, peek_sections};
+
+		let module = deserialize_file("./res/cases/v1/test5.wasm").expect("Should be deserialized");
+		let buf = serialize(module.clone()).expect("serialization to succeed");
+
+		let sections = peek_sections(&buf).expect("Should be peeked");
+		assert_eq!(sections.len(), module.sections().len());
+		for (&(id, len), section) in sections.iter().zip(module.sections()) {
+			let bytes = serialize(section.clone()).expect("serialization to succeed");
+			let len_bytes = serialize(VarUint32::from(len)).expect("serialization to succeed");
+			assert_eq!(id, bytes[0]);
+			assert_eq!(bytes.len(), 1 + len_bytes.len() + len);
+		}
+
+		assert!(matches!(peek_sections(&buf[..buf.len() - 1]), Err(Error::At { .. })));
+		assert!(matches!(peek_sections(&[0x00, 0x61, 0x73, 0x6e]), Err(Error::InvalidMagic)));
+	}
+
 	#[test]
 	fn peek() {
 		use super::peek_size;
@@ -939,6 +2461,549 @@ mod integration_tests {
 		assert_eq!(module.is_ok(), cfg!(feature = "atomics"));
 	}
 
+	#[cfg(feature = "atomics")]
+	#[test]
+	fn shared_memory_import() {
+		use super::super::{External, ImportEntry, MemoryType};
+
+		let bytes = [
+			0x03, b'e', b'n', b'v', // module
+			0x03, b'm', b'e', b'm', // field
+			0x02, // memory
+			0x03, 0x01, 0x02, // shared, min 1, max 2
+		];
+		let entry: ImportEntry = deserialize_buffer(&bytes).expect("valid import");
+		let mut memory_type = MemoryType::new(1, Some(2));
+		memory_type.set_shared(true);
+		let expected = ImportEntry::new("env".into(), "mem".into(), External::Memory(memory_type));
+		assert_eq!(entry, expected);
+		assert_eq!(serialize(entry).expect("serialization failed"), bytes);
+	}
+
+	#[test]
+	fn segment_counts() {
+		use super::super::Instruction;
+		use crate::builder;
+
+		let module = builder::module().build();
+		assert_eq!(module.element_segment_count(), 0);
+		assert_eq!(module.data_segment_count(), 0);
+
+		let module = builder::module()
+			.memory()
+			.with_min(1)
+			.build()
+			.data()
+			.offset(Instruction::I32Const(0))
+			.value(vec![1, 2])
+			.build()
+			.data()
+			.offset(Instruction::I32Const(16))
+			.value(vec![3])
+			.build()
+			.build();
+		assert_eq!(module.element_segment_count(), 0);
+		assert_eq!(module.data_segment_count(), 2);
+	}
+
+	#[cfg(feature = "atomics")]
+	#[test]
+	fn shared_memory_limits() {
+		use super::super::MemoryType;
+
+		// (memory 1 10 shared)
+		let memory_type: MemoryType =
+			deserialize_buffer(&[0x03, 0x01, 0x0a]).expect("valid limits");
+		assert!(memory_type.limits().shared());
+		assert_eq!(memory_type.limits().maximum(), Some(10));
+		assert_eq!(serialize(memory_type).expect("serialization failed"), [0x03, 0x01, 0x0a]);
+
+		let result = deserialize_buffer::<MemoryType>(&[0x02, 0x01]);
+		assert!(matches!(result, Err(Error::InvalidLimitsFlags(0x02))));
+
+		let mut memory_type = MemoryType::new(1, None);
+		memory_type.set_shared(true);
+		assert!(matches!(serialize(memory_type), Err(Error::InvalidLimitsFlags(0x02))));
+	}
+
+	#[test]
+	fn names_without_parsing() {
+		let module =
+			deserialize_file("./res/cases/v1/with_names.wasm").expect("Should be deserialized");
+		let names = module.names().expect("name section to exist");
+		let function_names = names.functions().expect("function names to exist").names();
+		assert_eq!(function_names.get(0).map(String::as_str), Some("elog"));
+		let parsed = module.parse_names().expect("names to parse");
+		assert_eq!(parsed.names(), Some(names));
+
+		assert!(Module::default().try_names().expect("no name section to be fine").is_none());
+
+		let mut malformed = Module::default();
+		malformed.set_custom_section("name", vec![0x01, 0x05, 0x01]);
+		assert!(malformed.try_names().is_err());
+		assert!(malformed.names().is_none());
+	}
+
+	#[test]
+	fn code_metrics() {
+		use super::super::{Instruction, Instructions, Local, ValueType};
+		use crate::builder;
+
+		assert_eq!(Module::default().instruction_count(), 0);
+		assert_eq!(Module::default().code_size().expect("Should be measured"), 0);
+
+		let module = builder::module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.with_instructions(Instructions::new(vec![
+				Instruction::I32Const(1),
+				Instruction::Drop,
+				Instruction::End,
+			]))
+			.build()
+			.build()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.with_locals(vec![Local::new(2, ValueType::I64)])
+			.with_instructions(Instructions::new(vec![Instruction::Nop, Instruction::End]))
+			.build()
+			.build()
+			.build();
+
+		assert_eq!(module.instruction_count(), 5);
+		// size, no locals, i32.const 1, drop, end
+		// size, one local entry of 2 i64, nop, end
+		assert_eq!(module.code_size().expect("Should be measured"), 6 + 6);
+	}
+
+	#[test]
+	fn retain_valid_exports() {
+		use super::super::{ExportEntry, Internal};
+		use crate::builder;
+
+		let mut module = builder::module()
+			.function()
+			.signature()
+			.build()
+			.build()
+			.function()
+			.signature()
+			.build()
+			.build()
+			.with_export(ExportEntry::new("first".into(), Internal::Function(0)))
+			.with_export(ExportEntry::new("second".into(), Internal::Function(1)))
+			.with_export(ExportEntry::new("memory".into(), Internal::Memory(0)))
+			.build();
+
+		// Drop the second function without fixing up the exports.
+		module
+			.function_section_mut()
+			.expect("Should have function section")
+			.entries_mut()
+			.pop();
+		module.code_section_mut().expect("Should have code section").bodies_mut().pop();
+
+		let mut exports = module.export_section().expect("Should have export section").clone();
+		assert_eq!(exports.retain_valid(&module), 2);
+		assert_eq!(exports.entries(), &[ExportEntry::new("first".into(), Internal::Function(0))]);
+	}
+
+	#[test]
+	fn init_exprs() {
+		use super::super::{InitExpr, Instruction::*};
+		use crate::builder;
+
+		let module = builder::module()
+			.global()
+			.value_type()
+			.i32()
+			.init_expr(I32Const(42))
+			.build()
+			.memory()
+			.with_min(1)
+			.build()
+			.data()
+			.offset(I32Const(16))
+			.value(vec![1, 2])
+			.build()
+			.build();
+
+		let exprs: Vec<&InitExpr> = module.init_exprs().collect();
+		assert_eq!(exprs.len(), 2);
+		assert_eq!(exprs[0].code(), &[I32Const(42), End]);
+		assert_eq!(exprs[1].code(), &[I32Const(16), End]);
+	}
+
+	#[test]
+	fn ensure_data_count_section() {
+		use super::super::{deserialize_buffer, serialize, Instruction, Section};
+		use crate::builder;
+
+		let mut module = builder::module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.build()
+			.build()
+			.memory()
+			.with_min(1)
+			.build()
+			.data()
+			.offset(Instruction::I32Const(0))
+			.value(vec![1, 2])
+			.build()
+			.build();
+		module.ensure_data_count_section();
+		assert_eq!(module.data_count_section(), Some(1));
+
+		let segments = module.data_section_mut().expect("Should have data section").entries_mut();
+		segments.push(segments[0].clone());
+		module.ensure_data_count_section();
+		assert_eq!(module.data_count_section(), Some(2));
+
+		let bytes = serialize(module).expect("Should serialize");
+		let module: Module = deserialize_buffer(&bytes).expect("Should deserialize");
+		let position = |f: fn(&Section) -> bool| module.sections().iter().position(f).unwrap();
+		let data_count = position(|s| matches!(*s, Section::DataCount(_)));
+		assert!(data_count < position(|s| matches!(*s, Section::Code(_))));
+		assert!(data_count > position(|s| matches!(*s, Section::Memory(_))));
+	}
+
+	#[test]
+	fn order_sections() {
+		use super::super::{
+			serialize, CodeSection, CustomSection, FunctionSection, Section, TypeSection,
+		};
+
+		let mut module = Module::new(vec![
+			Section::Code(CodeSection::default()),
+			Section::Custom(CustomSection::new("after_code".into(), vec![1])),
+			Section::Function(FunctionSection::default()),
+			Section::Type(TypeSection::default()),
+			Section::Custom(CustomSection::new("after_type".into(), vec![2])),
+		]);
+		let unordered = {
+			let mut bytes = Vec::new();
+			module.clone().serialize_unordered(&mut bytes).expect("Should serialize");
+			bytes
+		};
+		let serialized = serialize(module.clone()).expect("Should serialize");
+
+		module.order_sections();
+		let names: Vec<&str> = module
+			.sections()
+			.iter()
+			.map(|section| match *section {
+				Section::Type(_) => "type",
+				Section::Function(_) => "function",
+				Section::Code(_) => "code",
+				Section::Custom(ref custom) => custom.name(),
+				_ => unreachable!(),
+			})
+			.collect();
+		assert_eq!(names, ["type", "after_type", "function", "code", "after_code"]);
+
+		assert_eq!(serialized, serialize(module).expect("Should serialize"));
+		assert_ne!(serialized, unordered);
+		assert_eq!(unordered[8], 0x0a);
+	}
+
+	#[test]
+	fn first_section_by_id() {
+		use super::super::{CustomSection, NameSection, Section};
+		use crate::builder;
+
+		let mut module = builder::module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.build()
+			.build()
+			.with_section(Section::Custom(CustomSection::new("producers".into(), vec![])))
+			.build();
+		assert!(matches!(module.first_section_by_id(0x0a), Some(Section::Code(_))));
+		assert!(matches!(module.first_section_by_id(0x00), Some(Section::Custom(_))));
+		assert!(module.first_section_by_id(0x0b).is_none());
+
+		let name = Section::Name(NameSection::new(None, None, None));
+		assert_eq!(name.id(), 0);
+		if let Some(Section::Custom(custom)) = module.first_section_by_id_mut(0) {
+			custom.set_name("renamed".into());
+		}
+		assert_eq!(module.custom_sections().next().map(|custom| custom.name()), Some("renamed"));
+	}
+
+	#[test]
+	fn limits_maximum() {
+		use super::super::{Error, ResizableLimits};
+
+		let mut limits = ResizableLimits::new(2, Some(10));
+		assert!(matches!(
+			limits.set_maximum(Some(1)),
+			Err(Error::InvalidLimits { initial: 2, maximum: 1 })
+		));
+		assert_eq!(limits.maximum(), Some(10));
+		limits.set_maximum(None).expect("Unbounded limits are valid");
+		assert_eq!(limits.maximum(), None);
+
+		limits.clamp_maximum(4);
+		assert_eq!(limits.maximum(), None);
+		limits.set_maximum(Some(10)).expect("Maximum above initial is valid");
+		limits.clamp_maximum(4);
+		assert_eq!(limits.maximum(), Some(4));
+		limits.clamp_maximum(8);
+		assert_eq!(limits.maximum(), Some(4));
+		limits.clamp_maximum(1);
+		assert_eq!(limits.maximum(), Some(2));
+		assert!(limits.validate().is_ok());
+	}
+
+	#[test]
+	fn remap_functions() {
+		use super::super::{ExportEntry, Instruction::*, Instructions, Internal};
+		use crate::builder;
+
+		let body = |instructions| {
+			builder::function()
+				.signature()
+				.build()
+				.body()
+				.with_instructions(Instructions::new(instructions))
+				.build()
+				.build()
+		};
+		let mut module = builder::module();
+		module.push_function(body(vec![Call(1), I32Const(0), CallIndirect(0, 0), End]));
+		module.push_function(body(vec![Call(0), End]));
+		let mut module = module
+			.with_export(ExportEntry::new("first".into(), Internal::Function(0)))
+			.table()
+			.with_min(2)
+			.with_element(0, vec![0, 1])
+			.build()
+			.with_start(1)
+			.build();
+
+		module.remap_functions(&|index| 1 - index);
+
+		let bodies = module.code_section().expect("Should have code section").bodies();
+		assert_eq!(bodies[0].code().elements(), &[Call(0), I32Const(0), CallIndirect(0, 0), End]);
+		assert_eq!(bodies[1].code().elements(), &[Call(1), End]);
+		let exports = module.export_section().expect("Should have export section").entries();
+		assert_eq!(exports[0].internal(), &Internal::Function(1));
+		let elements = module.elements_section().expect("Should have element section").entries();
+		assert_eq!(elements[0].members(), &[1, 0]);
+		assert_eq!(module.start_section(), Some(0));
+	}
+
+	#[test]
+	fn canonicalize() {
+		use super::super::{serialize, CustomSection, Section};
+		use crate::builder;
+
+		let custom = |name: &str, payload: &[u8]| {
+			Section::Custom(CustomSection::new(name.into(), payload.to_vec()))
+		};
+		let base = || {
+			builder::module()
+				.function()
+				.signature()
+				.build()
+				.body()
+				.build()
+				.build()
+				.export()
+				.field("main")
+				.internal()
+				.func(0)
+				.build()
+				.build()
+		};
+
+		let mut a = base();
+		a.sections_mut().insert(0, custom("producers", b"clang"));
+		a.sections_mut().push(custom("name", &[0, 5, 4, b'm', b'a', b'i', b'n']));
+		a.sections_mut().push(custom("build_id", &[1, 2, 3]));
+		let mut b = base();
+		b.sections_mut().insert(1, custom("name", &[0, 5, 4, b'm', b'a', b'i', b'n']));
+		b.sections_mut().push(custom("producers", b"rustc"));
+		assert_ne!(serialize(a.clone()).unwrap(), serialize(b.clone()).unwrap());
+
+		a.canonicalize(&["name"]);
+		b.canonicalize(&["name"]);
+		assert_eq!(a.custom_sections().count(), 1);
+		assert!(matches!(a.sections().last(), Some(Section::Custom(_))));
+		assert_eq!(serialize(a).unwrap(), serialize(b).unwrap());
+	}
+
+	#[test]
+	fn utf8_custom_section_name() {
+		use super::super::{deserialize_buffer, peek_size, Error};
+
+		// custom section named "é✓" (5 bytes) with a 2-byte payload
+		let mut bytes = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+		bytes.extend_from_slice(&[0x00, 0x08, 0x05, 0xc3, 0xa9, 0xe2, 0x9c, 0x93, 0x01, 0x02]);
+		bytes.extend_from_slice(&[0x01, 0x01, 0x00]); // empty type section
+
+		assert_eq!(peek_size(&bytes), bytes.len());
+		let module: Module = deserialize_buffer(&bytes).expect("Should deserialize");
+		let custom = module.custom_sections().next().expect("Should have custom section");
+		assert_eq!(custom.name(), "é✓");
+		assert_eq!(custom.payload(), &[0x01, 0x02]);
+		assert!(module.type_section().is_some());
+
+		// truncated multibyte sequence in the name
+		bytes[12] = 0x20;
+		let err = deserialize_buffer::<Module>(&bytes).unwrap_err();
+		assert!(matches!(err.without_offset(), Error::NonUtf8String));
+	}
+
+	#[test]
+	fn imported_globals() {
+		use super::super::{GlobalType, Instruction, ValueType};
+		use crate::builder;
+
+		let module = builder::module()
+			.import()
+			.module("env")
+			.field("g0")
+			.external()
+			.global(ValueType::I32, false)
+			.build()
+			.import()
+			.module("env")
+			.field("f")
+			.external()
+			.func(0)
+			.build()
+			.import()
+			.module("env")
+			.field("g1")
+			.external()
+			.global(ValueType::F64, true)
+			.build()
+			.global()
+			.value_type()
+			.i64()
+			.init_expr(Instruction::I64Const(1))
+			.build()
+			.build();
+
+		assert_eq!(
+			module.imported_globals(),
+			[&GlobalType::new(ValueType::I32, false), &GlobalType::new(ValueType::F64, true)]
+		);
+	}
+
+	#[test]
+	fn remove_unused_types() {
+		use super::super::{
+			CodeSection, External, Func, FuncBody, FunctionSection, FunctionType, ImportEntry,
+			ImportSection, Instruction::*, Instructions, Section, Type, TypeSection, ValueType,
+		};
+
+		let signature = |params| Type::Function(FunctionType::new(params, vec![]));
+		let mut module = Module::new(vec![
+			// the (i32) -> () signature is not referenced
+			Section::Type(TypeSection::with_types(vec![
+				signature(vec![]),
+				signature(vec![ValueType::I32]),
+				signature(vec![ValueType::I64]),
+			])),
+			Section::Import(ImportSection::with_entries(vec![ImportEntry::new(
+				"env".into(),
+				"f".into(),
+				External::Function(2),
+			)])),
+			Section::Function(FunctionSection::with_entries(vec![Func::new(0)])),
+			Section::Code(CodeSection::with_bodies(vec![FuncBody::new(
+				vec![],
+				Instructions::new(vec![I32Const(0), CallIndirect(2, 0), End]),
+			)])),
+		]);
+
+		assert_eq!(module.unused_type_indices(), [1]);
+		assert_eq!(module.remove_unused_types(), 1);
+		assert!(module.unused_type_indices().is_empty());
+
+		let types = module.type_section().expect("Should have type section").types();
+		assert_eq!(types, &[signature(vec![]), signature(vec![ValueType::I64])]);
+		let imports = module.import_section().expect("Should have import section").entries();
+		assert_eq!(imports[0].external(), &External::Function(1));
+		let body = &module.code_section().expect("Should have code section").bodies()[0];
+		assert_eq!(body.code().elements(), &[I32Const(0), CallIndirect(1, 0), End]);
+		assert_eq!(module.function_section().unwrap().entries(), &[Func::new(0)]);
+	}
+
+	#[test]
+	fn dedup_types() {
+		use super::super::{
+			CodeSection, External, Func, FuncBody, FunctionSection, FunctionType, ImportEntry,
+			ImportSection, Instruction::*, Instructions, Section, Type, TypeSection, ValueType,
+		};
+
+		let returns_i32 = Type::Function(FunctionType::new(vec![], vec![ValueType::I32]));
+		let takes_i64 = Type::Function(FunctionType::new(vec![ValueType::I64], vec![]));
+		let mut module = Module::new(vec![
+			Section::Type(TypeSection::with_types(vec![
+				returns_i32.clone(),
+				takes_i64.clone(),
+				returns_i32.clone(),
+			])),
+			Section::Import(ImportSection::with_entries(vec![ImportEntry::new(
+				"env".into(),
+				"f".into(),
+				External::Function(2),
+			)])),
+			Section::Function(FunctionSection::with_entries(vec![Func::new(2), Func::new(1)])),
+			Section::Code(CodeSection::with_bodies(vec![
+				FuncBody::new(vec![], Instructions::new(vec![I32Const(0), End])),
+				FuncBody::new(
+					vec![],
+					Instructions::new(vec![I32Const(0), CallIndirect(2, 0), Drop, End]),
+				),
+			])),
+		]);
+
+		assert_eq!(module.dedup_types(), 1);
+		assert_eq!(module.dedup_types(), 0);
+
+		let types = module.type_section().expect("Should have type section").types();
+		assert_eq!(types, &[returns_i32, takes_i64]);
+		let imports = module.import_section().expect("Should have import section").entries();
+		assert_eq!(imports[0].external(), &External::Function(0));
+		let functions = module.function_section().expect("Should have function section");
+		assert_eq!(functions.entries(), &[Func::new(0), Func::new(1)]);
+		let body = &module.code_section().expect("Should have code section").bodies()[1];
+		assert_eq!(body.code().elements(), &[I32Const(0), CallIndirect(0, 0), Drop, End]);
+	}
+
+	#[test]
+	fn serialized_size() {
+		use super::super::{deserialize_file, serialize};
+
+		for file in ["hello", "test5", "with_names", "offset", "start_add"] {
+			let module = deserialize_file(format!("./res/cases/v1/{}.wasm", file))
+				.expect("Should be deserialized");
+			let size = module.serialized_size().expect("Should be serialized");
+			assert_eq!(size, serialize(module).expect("Should be serialized").len(), "{}", file);
+		}
+
+		let module = deserialize_file("./res/cases/v1/with_names.wasm")
+			.expect("Should be deserialized")
+			.parse_names()
+			.expect("Names to be parsed");
+		assert!(module.names_section().is_some());
+		let size = module.serialized_size().expect("Should be serialized");
+		assert_eq!(size, serialize(module).expect("Should be serialized").len());
+	}
+
 	#[test]
 	fn memory_space() {
 		let module =
@@ -946,6 +3011,264 @@ mod integration_tests {
 		assert_eq!(module.memory_space(), 2);
 	}
 
+	#[test]
+	fn serialize_to_and_deserialize_from() {
+		use super::super::{deserialize_from, serialize_to};
+
+		let module = deserialize_file("./res/cases/v1/test5.wasm").expect("Should be deserialized");
+
+		let mut cursor = std::io::Cursor::new(Vec::new());
+		serialize_to(&mut cursor, module.clone()).expect("serialization to succeed");
+		cursor.set_position(0);
+		let module_new = deserialize_from(&mut cursor).expect("deserialization to succeed");
+
+		assert_eq!(module, module_new);
+	}
+
+	#[test]
+	fn error_offset() {
+		let mut bytes = [
+			0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+			0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+			0x03, 0x02, 0x01, 0x00, // function section
+			0x0a, 0x07, 0x01, 0x05, 0x00, // code section, single body without locals
+			0x41, 0x01, // i32.const 1
+			0x1a, // drop
+			0x0b, // end
+		];
+		assert!(Module::from_bytes(bytes).is_ok());
+
+		bytes[25] = 0x27;
+		let err = Module::from_bytes(bytes).expect_err("Unknown opcode should be rejected");
+		match err {
+			Error::At { offset: 26, ref inner } => {
+				assert!(matches!(**inner, Error::UnknownOpcode(0x27)))
+			},
+			ref other => panic!("Unexpected error {:?}", other),
+		}
+		assert!(format!("{}", err).ends_with(" at byte 26"));
+	}
+
+	#[test]
+	fn deserialize_tolerant() {
+		use super::super::Instruction;
+
+		fn hook(prefix: Option<u8>, opcode: u8) -> Option<usize> {
+			match (prefix, opcode) {
+				(None, 0xf0) => Some(2),
+				(Some(0xfb), 0x01) => Some(1),
+				_ => None,
+			}
+		}
+
+		let bytes = [
+			0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+			0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+			0x03, 0x02, 0x01, 0x00, // function section
+			0x0a, 0x0a, 0x01, 0x08, 0x00, // code section, single body without locals
+			0xf0, 0xaa, 0xbb, // unknown opcode with two bytes of immediates
+			0xfb, 0x01, 0xcc, // unknown prefixed opcode with a single byte of immediates
+			0x0b, // end
+		];
+
+		assert!(Module::from_bytes(bytes).is_err());
+
+		let module = Module::deserialize_tolerant(&mut &bytes[..], hook)
+			.expect("Module with known unknown opcodes should be deserialized");
+		let code = module.code_section().expect("code section").bodies()[0].code().elements();
+		assert_eq!(
+			code[0],
+			Instruction::Unknown {
+				opcode: 0xf0,
+				prefix: None,
+				immediate_bytes: vec![0xaa, 0xbb].into_boxed_slice()
+			}
+		);
+		assert_eq!(format!("{}", code[1]), "unknown 0xfb 0x01");
+		assert_eq!(module.into_bytes().expect("Failed to serialize"), bytes);
+
+		let mut unregistered = bytes;
+		unregistered[27] = 0x02;
+		assert!(Module::deserialize_tolerant(&mut &unregistered[..], hook).is_err());
+	}
+
+	#[test]
+	fn validate_start() {
+		use super::super::ValueType;
+		use crate::builder;
+
+		let mut module = builder::module()
+			.function()
+			.main()
+			.signature()
+			.build()
+			.body()
+			.build()
+			.build()
+			.function()
+			.signature()
+			.with_param(ValueType::I32)
+			.build()
+			.body()
+			.build()
+			.build()
+			.build();
+		assert!(module.validate_start().is_ok());
+
+		module.set_start_section(1);
+		assert!(module.validate_start().is_err());
+
+		module.set_start_section(2);
+		assert!(matches!(
+			module.validate_start(),
+			Err(Error::FunctionIndexOutOfBounds { index: 2, len: 2 })
+		));
+
+		module.clear_start_section();
+		assert!(module.validate_start().is_ok());
+	}
+
+	#[test]
+	fn max_block_depth() {
+		use super::super::{BlockType, FuncBody, Instruction, Instructions};
+
+		let nested = FuncBody::new(
+			vec![],
+			Instructions::new(vec![
+				Instruction::Block(BlockType::NoResult),
+				Instruction::Loop(BlockType::NoResult),
+				Instruction::Block(BlockType::NoResult),
+				Instruction::End,
+				Instruction::End,
+				Instruction::End,
+				Instruction::Block(BlockType::NoResult),
+				Instruction::End,
+				Instruction::End,
+			]),
+		);
+		assert_eq!(nested.max_block_depth(), 3);
+		assert_eq!(FuncBody::empty().max_block_depth(), 0);
+
+		let module = Module::new(vec![Section::Code(CodeSection::with_bodies(vec![
+			FuncBody::new(vec![], Instructions::new(vec![Instruction::End])),
+			nested,
+		]))]);
+		assert_eq!(module.max_block_depth(), 3);
+		assert_eq!(Module::default().max_block_depth(), 0);
+	}
+
+	#[test]
+	fn check_init_order() {
+		use super::{
+			super::{
+				DataSection, DataSegment, GlobalEntry, GlobalSection, GlobalType, ImportEntry,
+				ImportSection, InitExpr, Instruction, ValueType,
+			},
+			External,
+		};
+
+		let segment_reading_global = |index| {
+			DataSection::with_entries(vec![DataSegment::new(
+				0,
+				Some(InitExpr::new(vec![Instruction::GetGlobal(index), Instruction::End])),
+				vec![0x2a],
+			)])
+		};
+
+		let imported = ImportEntry::new(
+			"env".into(),
+			"offset".into(),
+			External::Global(GlobalType::new(ValueType::I32, false)),
+		);
+		let defined = GlobalEntry::new(
+			GlobalType::new(ValueType::I32, false),
+			InitExpr::new(vec![Instruction::I32Const(16), Instruction::End]),
+		);
+		let mut module = Module::new(vec![
+			Section::Import(ImportSection::with_entries(vec![imported])),
+			Section::Global(GlobalSection::with_entries(vec![defined])),
+			Section::Data(segment_reading_global(0)),
+		]);
+		assert!(module.check_init_order().is_ok());
+
+		*module.data_section_mut().expect("data section") = segment_reading_global(1);
+		assert!(matches!(
+			module.check_init_order(),
+			Err(Error::Other("Segment offset references a non-imported global"))
+		));
+
+		*module.data_section_mut().expect("data section") = segment_reading_global(2);
+		assert!(matches!(
+			module.check_init_order(),
+			Err(Error::GlobalIndexOutOfBounds { index: 2, len: 2 })
+		));
+	}
+
+	#[test]
+	fn data_count_consistency() {
+		let bytes = |count: u8| {
+			[
+				0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+				0x05, 0x03, 0x01, 0x00, 0x01, // memory section
+				0x0c, 0x01, count, // data count section
+				0x0b, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0x2a, // data section
+			]
+		};
+
+		let module = Module::from_bytes(bytes(1)).expect("Matching data count should be accepted");
+		assert_eq!(module.data_count_section(), Some(1));
+
+		match Module::from_bytes(bytes(2)) {
+			Err(Error::InconsistentDataCount) => {},
+			other => panic!("Expected InconsistentDataCount error, got {:?}", other),
+		}
+	}
+
+	#[cfg(feature = "exceptions")]
+	#[test]
+	fn tag_section_roundtrip() {
+		use super::super::{Internal, TagType};
+
+		let bytes = [
+			0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+			0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+			0x0d, 0x03, 0x01, 0x00, 0x00, // tag section
+			0x07, 0x05, 0x01, 0x01, b'e', 0x04, 0x00, // export section
+		];
+		let module = Module::from_bytes(bytes).expect("Failed to deserialize");
+		assert_eq!(module.tag_section().expect("tag section").entries(), &[TagType::new(0)]);
+		assert_eq!(
+			module.export_section().expect("export section").entries()[0].internal(),
+			&Internal::Tag(0)
+		);
+		assert_eq!(module.into_bytes().expect("Failed to serialize"), bytes);
+	}
+
+	#[test]
+	fn imported_memory_and_table_space() {
+		use crate::builder;
+
+		let module = builder::module()
+			.import()
+			.path("env", "memory")
+			.external()
+			.memory(1, None)
+			.build()
+			.import()
+			.path("env", "table")
+			.external()
+			.table(1, None)
+			.build()
+			.table()
+			.with_min(1)
+			.build()
+			.build();
+
+		assert!(module.memory_section().is_none());
+		assert_eq!(module.memory_space(), 1);
+		assert_eq!(module.table_space(), 2);
+	}
+
 	#[test]
 	fn add_custom_section() {
 		let mut module =
@@ -985,7 +3308,7 @@ mod integration_tests {
 		assert_eq!(module.start_section().expect("Did not find any start section"), 0);
 
 		let sections = module.sections().iter().map(|s| s.order()).collect::<Vec<_>>();
-		assert_eq!(sections, vec![1, 2, 3, 6, 7, 8, 9, 11, 12]);
+		assert_eq!(sections, vec![1, 2, 3, 7, 8, 9, 10, 12, 13]);
 	}
 
 	#[test]
@@ -994,14 +3317,14 @@ mod integration_tests {
 			.expect("failed to deserialize");
 
 		let sections = module.sections().iter().map(|s| s.order()).collect::<Vec<_>>();
-		assert_eq!(sections, vec![1, 2, 3, 6, 7, 9, 11, 12, 0]);
+		assert_eq!(sections, vec![1, 2, 3, 7, 8, 10, 12, 13, 0]);
 
 		assert!(module.start_section().is_none());
 		module.set_start_section(0);
 		assert_eq!(module.start_section().expect("Dorder not find any start section"), 0);
 
 		let sections = module.sections().iter().map(|s| s.order()).collect::<Vec<_>>();
-		assert_eq!(sections, vec![1, 2, 3, 6, 7, 8, 9, 11, 12, 0]);
+		assert_eq!(sections, vec![1, 2, 3, 7, 8, 9, 10, 12, 13, 0]);
 	}
 
 	#[test]
@@ -1071,6 +3394,30 @@ mod integration_tests {
 		assert!(deserialize_buffer::<Module>(&serialized).is_ok());
 	}
 
+	#[test]
+	fn global_init_value() {
+		use super::super::{ConstValue, Instruction::*};
+		use crate::builder;
+
+		let module = builder::module()
+			.global()
+			.value_type()
+			.i32()
+			.init_expr(I32Const(42))
+			.build()
+			.global()
+			.value_type()
+			.i32()
+			.init_expr(GetGlobal(0))
+			.build()
+			.build();
+
+		assert_eq!(module.global_init_value(0), Some(ConstValue::I32(42)));
+		assert_eq!(module.global_init_value(1), Some(ConstValue::I32(42)));
+		assert_eq!(module.global_init_value(2), None);
+		assert_eq!(format!("{}", ConstValue::I32(42)), "i32.const 42");
+	}
+
 	#[test]
 	fn serialization_roundtrip() {
 		let module = deserialize_file("./res/cases/v1/test.wasm").expect("failed to deserialize");
@@ -1,13 +1,15 @@
 use crate::io;
-use alloc::string::String;
+use alloc::{string::String, vec::Vec};
 
 use super::{
-	index_map::IndexMap, Deserialize, Error, Module, Serialize, Type, VarUint32, VarUint7,
+	index_map::IndexMap, serialize, CustomSection, Deserialize, Error, Module, Serialize, Type,
+	VarUint32, VarUint7,
 };
 
 const NAME_TYPE_MODULE: u8 = 0;
 const NAME_TYPE_FUNCTION: u8 = 1;
 const NAME_TYPE_LOCAL: u8 = 2;
+const NAME_TYPE_LABEL: u8 = 3;
 
 /// Debug name information.
 #[derive(Clone, Debug, PartialEq)]
@@ -20,6 +22,9 @@ pub struct NameSection {
 
 	/// Local name subsection.
 	locals: Option<LocalNameSubsection>,
+
+	/// Label name subsection.
+	labels: Option<LabelNameSubsection>,
 }
 
 impl NameSection {
@@ -29,7 +34,7 @@ impl NameSection {
 		functions: Option<FunctionNameSubsection>,
 		locals: Option<LocalNameSubsection>,
 	) -> Self {
-		Self { module, functions, locals }
+		Self { module, functions, locals, labels: None }
 	}
 
 	/// Module name subsection of this section.
@@ -61,6 +66,23 @@ impl NameSection {
 	pub fn locals_mut(&mut self) -> &mut Option<LocalNameSubsection> {
 		&mut self.locals
 	}
+
+	/// Label name subsection of this section.
+	pub fn labels(&self) -> Option<&LabelNameSubsection> {
+		self.labels.as_ref()
+	}
+
+	/// Label name subsection of this section (mutable).
+	pub fn labels_mut(&mut self) -> &mut Option<LabelNameSubsection> {
+		&mut self.labels
+	}
+
+	/// Serialize this section into a `name` custom section.
+	///
+	/// Subsections are written in canonical order: module, functions, locals, labels.
+	pub fn to_custom_section(&self) -> Result<CustomSection, Error> {
+		Ok(CustomSection::new("name".into(), serialize(self.clone())?))
+	}
 }
 
 impl NameSection {
@@ -69,6 +91,7 @@ impl NameSection {
 		let mut module_name: Option<ModuleNameSubsection> = None;
 		let mut function_names: Option<FunctionNameSubsection> = None;
 		let mut local_names: Option<LocalNameSubsection> = None;
+		let mut label_names: Option<LabelNameSubsection> = None;
 
 		while let Ok(raw_subsection_type) = VarUint7::deserialize(rdr) {
 			let subsection_type = raw_subsection_type.into();
@@ -97,16 +120,27 @@ impl NameSection {
 					local_names = Some(LocalNameSubsection::deserialize(module, rdr)?);
 				},
 
+				NAME_TYPE_LABEL => {
+					if label_names.is_some() {
+						return Err(Error::DuplicatedNameSubsections(NAME_TYPE_LABEL))
+					}
+					label_names = Some(LabelNameSubsection::deserialize(module, rdr)?);
+				},
+
 				_ => {
 					// Consume the entire subsection size and drop it. This allows other sections to still be
 					// consumed if there are any.
-					let mut buf = vec![0; size];
-					rdr.read(&mut buf)?;
+					let _ = buffered_read!(super::UNPARSED_BUFFER_LENGTH, size, rdr);
 				},
 			};
 		}
 
-		Ok(Self { module: module_name, functions: function_names, locals: local_names })
+		Ok(Self {
+			module: module_name,
+			functions: function_names,
+			locals: local_names,
+			labels: label_names,
+		})
 	}
 }
 
@@ -142,6 +176,12 @@ impl Serialize for NameSection {
 			serialize_subsection(wtr, NAME_TYPE_LOCAL, &buffer)?;
 		}
 
+		if let Some(label_name_subsection) = self.labels {
+			let mut buffer = vec![];
+			label_name_subsection.serialize(&mut buffer)?;
+			serialize_subsection(wtr, NAME_TYPE_LABEL, &buffer)?;
+		}
+
 		Ok(())
 	}
 }
@@ -289,6 +329,54 @@ impl Serialize for LocalNameSubsection {
 	}
 }
 
+/// The names of the labels in this module's functions.
+///
+/// Part of the extended name section proposal.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LabelNameSubsection {
+	label_names: IndexMap<NameMap>,
+}
+
+impl LabelNameSubsection {
+	/// A map from function indices to a map from label indices to names.
+	pub fn label_names(&self) -> &IndexMap<NameMap> {
+		&self.label_names
+	}
+
+	/// A map from function indices to a map from label indices to names
+	/// (mutable).
+	pub fn label_names_mut(&mut self) -> &mut IndexMap<NameMap> {
+		&mut self.label_names
+	}
+
+	/// Deserialize names, making sure that all names correspond to functions
+	/// and that label indices are bounded by the function code length.
+	pub fn deserialize<R: io::Read>(
+		module: &Module,
+		rdr: &mut R,
+	) -> Result<LabelNameSubsection, Error> {
+		let max_entry_space = module.functions_space();
+
+		let max_labels = module
+			.code_section()
+			.map(|cs| cs.bodies().iter().map(|f| f.code().elements().len()).max().unwrap_or(0))
+			.unwrap_or(0);
+
+		let deserialize_labels = |_: u32, rdr: &mut R| IndexMap::deserialize(max_labels, rdr);
+
+		let label_names = IndexMap::deserialize_with(max_entry_space, &deserialize_labels, rdr)?;
+		Ok(LabelNameSubsection { label_names })
+	}
+}
+
+impl Serialize for LabelNameSubsection {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, wtr: &mut W) -> Result<(), Error> {
+		self.label_names.serialize(wtr)
+	}
+}
+
 /// A map from indices to names.
 pub type NameMap = IndexMap<String>;
 
@@ -353,6 +441,54 @@ mod tests {
 		serialize_test(name_section);
 	}
 
+	#[test]
+	fn label_names_roundtrip() {
+		use super::super::{BlockType, Instruction, Instructions};
+		use crate::builder;
+
+		let module = builder::module()
+			.function()
+			.signature()
+			.build()
+			.body()
+			.with_instructions(Instructions::new(vec![
+				Instruction::Block(BlockType::NoResult),
+				Instruction::Loop(BlockType::NoResult),
+				Instruction::End,
+				Instruction::End,
+				Instruction::End,
+			]))
+			.build()
+			.build()
+			.build();
+
+		let mut labels = NameMap::default();
+		labels.insert(0, "outer".to_string());
+		labels.insert(1, "retry".to_string());
+		let mut label_name_subsection = LabelNameSubsection::default();
+		label_name_subsection.label_names_mut().insert(0, labels);
+
+		let mut original = NameSection::new(None, None, None);
+		*original.labels_mut() = Some(label_name_subsection);
+
+		let buffer = serialize_test(original.clone());
+		assert_eq!(buffer[0], NAME_TYPE_LABEL);
+
+		let deserialized =
+			NameSection::deserialize(&module, &mut crate::io::Cursor::new(&buffer[..]))
+				.expect("Should be deserialized");
+		assert_eq!(deserialized, original);
+	}
+
+	#[test]
+	fn deserialize_unknown_subsection_with_huge_length() {
+		let module = Module::default();
+		// subsection type 9, declared size u32::MAX, only two bytes of payload
+		let payload = [0x09, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x00, 0x00];
+		let res = NameSection::deserialize(&module, &mut crate::io::Cursor::new(&payload[..]));
+		assert!(res.is_err());
+	}
+
 	#[test]
 	fn deserialize_local_names() {
 		let module = super::super::deserialize_file("./res/cases/v1/names_with_imports.wasm")
@@ -369,4 +505,26 @@ mod tests {
 		let locals = local_names.local_names().get(1).expect("entry #1 should be present");
 		assert_eq!(locals.get(0).expect("entry #0 should be present"), "def");
 	}
+
+	#[test]
+	fn rename_function_roundtrip() {
+		let mut module = super::super::deserialize_file("./res/cases/v1/with_names.wasm")
+			.expect("Should be deserialized");
+		let mut names = module.names().expect("Should have name section");
+		let function_names = names.functions_mut().as_mut().expect("Should have function names");
+		function_names.names_mut().insert(0, "renamed".to_string());
+
+		let custom = names.to_custom_section().expect("Should be serialized");
+		assert_eq!(custom.name(), "name");
+		module.set_names(names.clone());
+
+		let module = Module::from_bytes(module.into_bytes().expect("Should be serialized"))
+			.expect("Should be deserialized");
+		let custom_sections: Vec<_> = module.custom_sections().collect();
+		assert_eq!(custom_sections, [&custom]);
+		let reparsed = module.names().expect("Should have name section");
+		assert_eq!(reparsed, names);
+		let function_names = reparsed.functions().expect("Should have function names").names();
+		assert_eq!(function_names.get(0).map(String::as_str), Some("renamed"));
+	}
 }
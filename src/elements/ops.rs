@@ -1,10 +1,10 @@
 use super::{
 	BlockType, CountedList, CountedListWriter, Deserialize, Error, Serialize, Uint32, Uint64,
-	Uint8, VarInt32, VarInt64, VarUint32,
+	Uint8, ValueType, VarInt32, VarInt64, VarUint32,
 };
 use crate::io;
 use alloc::{boxed::Box, vec::Vec};
-use core::fmt;
+use core::{fmt, ops::Range};
 
 /// List of instructions (usually inside a block section).
 #[derive(Debug, Clone, PartialEq)]
@@ -30,22 +30,122 @@ impl Instructions {
 	pub fn elements_mut(&mut self) -> &mut Vec<Instruction> {
 		&mut self.0
 	}
+
+	/// Insert an instruction at position `at`.
+	///
+	/// Fails if `at` is out of bounds or if the instruction would be placed after the
+	/// terminating `Instruction::End`.
+	pub fn insert(&mut self, at: usize, instruction: Instruction) -> Result<(), Error> {
+		self.check_edit(at..at)?;
+		self.0.insert(at, instruction);
+		Ok(())
+	}
+
+	/// Replace instructions in `range` with the ones from `instructions`.
+	///
+	/// Fails if `range` is out of bounds or if the edit would remove the terminating
+	/// `Instruction::End` or place instructions after it.
+	pub fn splice<I>(&mut self, range: Range<usize>, instructions: I) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = Instruction>,
+	{
+		self.check_edit(range.clone())?;
+		self.0.splice(range, instructions);
+		Ok(())
+	}
+
+	/// Remove all `Instruction::Nop`s, leaving the block structure intact.
+	pub fn strip_nops(&mut self) {
+		self.0.retain(|instruction| *instruction != Instruction::Nop);
+	}
+
+	/// Check that blocks are properly nested and the sequence is terminated.
+	///
+	/// Every `block`, `loop` and `if` must be closed by a matching `end`, `else` may only
+	/// appear once directly inside an `if`, and the sequence must end with a single `end`
+	/// closing the whole body, with nothing after it.
+	pub fn check_structure(&self) -> Result<(), Error> {
+		// Openers of the blocks enclosing the current instruction, updated by `else`
+		// (and `catch` with the `exceptions` feature).
+		let mut open: Vec<&Instruction> = Vec::new();
+		let mut terminated = false;
+		for instruction in &self.0 {
+			if terminated {
+				return Err(Error::Other("Instructions after the final `end`"))
+			}
+			match *instruction {
+				Instruction::Else => match open.last_mut() {
+					Some(opener @ &mut &Instruction::If(_)) => *opener = instruction,
+					_ => return Err(Error::Other("`else` outside of an `if` block")),
+				},
+				#[cfg(feature = "exceptions")]
+				Instruction::Exception(ExceptionInstruction::Catch(_)) |
+				Instruction::Exception(ExceptionInstruction::CatchAll) => match open.last_mut() {
+					Some(
+						opener @ &mut &Instruction::Exception(
+							ExceptionInstruction::Try(_) | ExceptionInstruction::Catch(_),
+						),
+					) => *opener = instruction,
+					_ => return Err(Error::Other("`catch` outside of a `try` block")),
+				},
+				#[cfg(feature = "exceptions")]
+				Instruction::Exception(ExceptionInstruction::Delegate(_)) => match open.pop() {
+					Some(&Instruction::Exception(ExceptionInstruction::Try(_))) => {},
+					_ => return Err(Error::Other("`delegate` outside of a `try` block")),
+				},
+				Instruction::End => terminated = open.pop().is_none(),
+				ref instruction if instruction.is_block() => open.push(instruction),
+				_ => {},
+			}
+		}
+
+		if !open.is_empty() {
+			return Err(Error::Other("Block without matching `end`"))
+		}
+		if !terminated {
+			return Err(Error::Other("Instructions are not terminated by `end`"))
+		}
+		Ok(())
+	}
+
+	fn check_edit(&self, range: Range<usize>) -> Result<(), Error> {
+		if range.start > range.end || range.end > self.0.len() {
+			return Err(Error::Other("Instruction range is out of bounds"))
+		}
+		if range.end == self.0.len() && self.0.last() == Some(&Instruction::End) {
+			return Err(Error::Other("Edit would break the terminating `end` instruction"))
+		}
+		Ok(())
+	}
 }
 
 impl Deserialize for Instructions {
 	type Error = Error;
 
 	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
+		Instructions::deserialize_with(reader, ParseConfig::default())
+	}
+}
+
+impl Instructions {
+	pub(crate) fn deserialize_with<R: io::Read>(
+		reader: &mut R,
+		config: ParseConfig,
+	) -> Result<Self, Error> {
 		let mut instructions = Vec::new();
 		let mut block_count = 1usize;
 
 		loop {
-			let instruction = Instruction::deserialize(reader)?;
+			let instruction = Instruction::deserialize_with(reader, config.unknown_opcode_hook)?;
 			if instruction.is_terminal() {
 				block_count -= 1;
 			} else if instruction.is_block() {
 				block_count =
 					block_count.checked_add(1).ok_or(Error::Other("too many instructions"))?;
+				// The function body itself is not counted as a nesting level.
+				if block_count - 1 > config.max_block_depth {
+					return Err(Error::Other("block nesting too deep"))
+				}
 			}
 
 			instructions.push(instruction);
@@ -84,6 +184,73 @@ impl InitExpr {
 	pub fn code_mut(&mut self) -> &mut Vec<Instruction> {
 		&mut self.0
 	}
+
+	/// Value of the expression, if it is a single constant instruction followed by `End`.
+	///
+	/// Expressions referencing globals can't be evaluated without the module context
+	/// and yield `None`.
+	pub fn eval(&self) -> Option<ConstValue> {
+		match self.0[..] {
+			[Instruction::I32Const(v), Instruction::End] => Some(ConstValue::I32(v)),
+			[Instruction::I64Const(v), Instruction::End] => Some(ConstValue::I64(v)),
+			[Instruction::F32Const(v), Instruction::End] => Some(ConstValue::F32(v)),
+			[Instruction::F64Const(v), Instruction::End] => Some(ConstValue::F64(v)),
+			_ => None,
+		}
+	}
+
+	/// Value of the expression, resolving `get_global` through `globals`.
+	///
+	/// `globals` holds the values of the globals space, by index. Fails if the expression
+	/// is not a single constant or `get_global` instruction followed by `End`, or if the
+	/// referenced global is out of range.
+	pub fn eval_const(&self, globals: &[ConstValue]) -> Result<ConstValue, Error> {
+		match self.0[..] {
+			[Instruction::GetGlobal(index), Instruction::End] => globals
+				.get(index as usize)
+				.copied()
+				.ok_or(Error::GlobalIndexOutOfBounds { index, len: globals.len() as u32 }),
+			_ => self.eval().ok_or(Error::Other("Initialization expression is not constant")),
+		}
+	}
+}
+
+/// Value of a constant expression.
+///
+/// Floats are kept as their bit patterns, the same way `Instruction` stores them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConstValue {
+	/// 32-bit integer.
+	I32(i32),
+	/// 64-bit integer.
+	I64(i64),
+	/// 32-bit float (bit pattern).
+	F32(u32),
+	/// 64-bit float (bit pattern).
+	F64(u64),
+}
+
+impl ConstValue {
+	/// Type of the value.
+	pub fn value_type(&self) -> ValueType {
+		match *self {
+			ConstValue::I32(_) => ValueType::I32,
+			ConstValue::I64(_) => ValueType::I64,
+			ConstValue::F32(_) => ValueType::F32,
+			ConstValue::F64(_) => ValueType::F64,
+		}
+	}
+}
+
+impl fmt::Display for ConstValue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ConstValue::I32(v) => write!(f, "i32.const {}", v),
+			ConstValue::I64(v) => write!(f, "i64.const {}", v),
+			ConstValue::F32(v) => write!(f, "f32.const {}", v),
+			ConstValue::F64(v) => write!(f, "f64.const {}", v),
+		}
+	}
 }
 
 impl Deserialize for InitExpr {
@@ -309,8 +476,50 @@ pub enum Instruction {
 
 	#[cfg(feature = "bulk")]
 	Bulk(BulkInstruction),
+
+	#[cfg(feature = "exceptions")]
+	Exception(ExceptionInstruction),
+
+	/// Instruction not known to this crate, kept as is.
+	///
+	/// Only produced by tolerant deserialization, see `Instruction::deserialize_tolerant`.
+	Unknown {
+		/// Opcode of the instruction (following the prefix, if any).
+		opcode: u8,
+		/// Prefix byte of the instruction, if any.
+		prefix: Option<u8>,
+		/// Raw immediate operands.
+		immediate_bytes: Box<[u8]>,
+	},
+}
+
+/// Reports the length in bytes of the immediates of an instruction unknown to this crate,
+/// given its prefix (if any) and opcode. Returning `None` rejects the instruction.
+pub type UnknownOpcodeHook = fn(prefix: Option<u8>, opcode: u8) -> Option<usize>;
+
+/// Default limit of block nesting in function bodies, see [`ParseConfig::max_block_depth`].
+pub const DEFAULT_MAX_BLOCK_DEPTH: usize = 10_000;
+
+/// Options for parsing function bodies.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseConfig {
+	/// Hook for opcodes unknown to this crate, see `Instruction::deserialize_tolerant`.
+	///
+	/// Without a hook, unknown opcodes are rejected.
+	pub unknown_opcode_hook: Option<UnknownOpcodeHook>,
+	/// Maximum nesting depth of blocks, loops and ifs in a function body.
+	pub max_block_depth: usize,
+}
+
+impl Default for ParseConfig {
+	fn default() -> Self {
+		ParseConfig { unknown_opcode_hook: None, max_block_depth: DEFAULT_MAX_BLOCK_DEPTH }
+	}
 }
 
+/// Bytes from which on unknown opcodes are treated as prefixes of multi-byte opcodes.
+const UNKNOWN_PREFIX_START: u8 = 0xfb;
+
 #[allow(missing_docs)]
 #[cfg(feature = "atomics")]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -572,8 +781,20 @@ pub enum BulkInstruction {
 	TableCopy,
 }
 
-#[cfg(any(feature = "simd", feature = "atomics"))]
+#[allow(missing_docs)]
+#[cfg(feature = "exceptions")]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ExceptionInstruction {
+	Try(BlockType),
+	Catch(u32),
+	CatchAll,
+	Delegate(u32),
+	Throw(u32),
+	Rethrow(u32),
+}
+
+#[cfg(any(feature = "simd", feature = "atomics"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[allow(missing_docs)]
 pub struct MemArg {
 	pub align: u8,
@@ -590,14 +811,38 @@ pub struct BrTableData {
 impl Instruction {
 	/// Is this instruction starts the new block (which should end with terminal instruction).
 	pub fn is_block(&self) -> bool {
-		matches!(self, &Instruction::Block(_) | &Instruction::Loop(_) | &Instruction::If(_))
+		match self {
+			&Instruction::Block(_) | &Instruction::Loop(_) | &Instruction::If(_) => true,
+			#[cfg(feature = "exceptions")]
+			&Instruction::Exception(ExceptionInstruction::Try(_)) => true,
+			_ => false,
+		}
 	}
 
 	/// Is this instruction determines the termination of instruction sequence?
 	///
-	/// `true` for `Instruction::End`
+	/// `true` for `Instruction::End` and, with the `exceptions` feature, for `delegate`
+	/// which closes its `try` block instead of `end`.
 	pub fn is_terminal(&self) -> bool {
-		matches!(self, &Instruction::End)
+		match self {
+			&Instruction::End => true,
+			#[cfg(feature = "exceptions")]
+			&Instruction::Exception(ExceptionInstruction::Delegate(_)) => true,
+			_ => false,
+		}
+	}
+
+	/// Is this instruction allowed in a constant (initialization) expression?
+	pub fn is_constant(&self) -> bool {
+		matches!(
+			*self,
+			Instruction::I32Const(_) |
+				Instruction::I64Const(_) |
+				Instruction::F32Const(_) |
+				Instruction::F64Const(_) |
+				Instruction::GetGlobal(_) |
+				Instruction::End
+		)
 	}
 }
 
@@ -791,6 +1036,16 @@ pub mod opcodes {
 		pub const I64_EXTEND32_S: u8 = 0xc4;
 	}
 
+	#[cfg(feature = "exceptions")]
+	pub mod exceptions {
+		pub const TRY: u8 = 0x06;
+		pub const CATCH: u8 = 0x07;
+		pub const THROW: u8 = 0x08;
+		pub const RETHROW: u8 = 0x09;
+		pub const DELEGATE: u8 = 0x18;
+		pub const CATCH_ALL: u8 = 0x19;
+	}
+
 	#[cfg(feature = "atomics")]
 	pub mod atomics {
 		pub const ATOMIC_PREFIX: u8 = 0xfe;
@@ -1053,11 +1308,36 @@ impl Deserialize for Instruction {
 	type Error = Error;
 
 	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
+		Instruction::deserialize_with(reader, None)
+	}
+}
+
+impl Instruction {
+	/// Deserialize an instruction, keeping opcodes unknown to this crate as
+	/// `Instruction::Unknown` instead of failing.
+	///
+	/// Since the crate can't know the encoding of such instructions, `unknown_opcode_hook`
+	/// tells how many bytes of immediates follow the opcode. Unknown bytes starting from
+	/// `0xfb` are treated as prefixes and followed by a single opcode byte.
+	pub fn deserialize_tolerant<R: io::Read>(
+		reader: &mut R,
+		unknown_opcode_hook: UnknownOpcodeHook,
+	) -> Result<Self, Error> {
+		Instruction::deserialize_with(reader, Some(unknown_opcode_hook))
+	}
+
+	fn deserialize_with<R: io::Read>(
+		reader: &mut R,
+		unknown_opcode_hook: Option<UnknownOpcodeHook>,
+	) -> Result<Self, Error> {
 		use self::{opcodes::*, Instruction::*};
 
 		#[cfg(feature = "sign_ext")]
 		use self::opcodes::sign_ext::*;
 
+		#[cfg(feature = "exceptions")]
+		use self::opcodes::exceptions::*;
+
 		let val: u8 = Uint8::deserialize(reader)?.into();
 
 		Ok(match val {
@@ -1387,11 +1667,45 @@ impl Deserialize for Instruction {
 			#[cfg(feature = "bulk")]
 			bulk::BULK_PREFIX => return deserialize_bulk(reader),
 
-			_ => return Err(Error::UnknownOpcode(val)),
+			#[cfg(feature = "exceptions")]
+			TRY => Exception(ExceptionInstruction::Try(BlockType::deserialize(reader)?)),
+			#[cfg(feature = "exceptions")]
+			CATCH => Exception(ExceptionInstruction::Catch(VarUint32::deserialize(reader)?.into())),
+			#[cfg(feature = "exceptions")]
+			CATCH_ALL => Exception(ExceptionInstruction::CatchAll),
+			#[cfg(feature = "exceptions")]
+			DELEGATE => Exception(ExceptionInstruction::Delegate(VarUint32::deserialize(reader)?.into())),
+			#[cfg(feature = "exceptions")]
+			THROW => Exception(ExceptionInstruction::Throw(VarUint32::deserialize(reader)?.into())),
+			#[cfg(feature = "exceptions")]
+			RETHROW => Exception(ExceptionInstruction::Rethrow(VarUint32::deserialize(reader)?.into())),
+
+			_ => match unknown_opcode_hook {
+				Some(hook) => return deserialize_unknown(reader, val, hook),
+				None => return Err(Error::UnknownOpcode(val)),
+			},
 		})
 	}
 }
 
+fn deserialize_unknown<R: io::Read>(
+	reader: &mut R,
+	val: u8,
+	hook: UnknownOpcodeHook,
+) -> Result<Instruction, Error> {
+	let (prefix, opcode) = if val >= UNKNOWN_PREFIX_START {
+		(Some(val), Uint8::deserialize(reader)?.into())
+	} else {
+		(None, val)
+	};
+
+	let immediates_len = hook(prefix, opcode).ok_or(Error::UnknownOpcode(val))?;
+	let mut immediate_bytes = vec![0u8; immediates_len];
+	reader.read(&mut immediate_bytes)?;
+
+	Ok(Instruction::Unknown { opcode, prefix, immediate_bytes: immediate_bytes.into_boxed_slice() })
+}
+
 #[cfg(feature = "atomics")]
 fn deserialize_atomic<R: io::Read>(reader: &mut R) -> Result<Instruction, Error> {
 	use self::{opcodes::atomics::*, AtomicsInstruction::*};
@@ -1656,10 +1970,11 @@ fn deserialize_bulk<R: io::Read>(reader: &mut R) -> Result<Instruction, Error> {
 	let val: u8 = Uint8::deserialize(reader)?.into();
 	Ok(Instruction::Bulk(match val {
 		MEMORY_INIT => {
+			let seg = VarUint32::deserialize(reader)?.into();
 			if u8::from(Uint8::deserialize(reader)?) != 0 {
 				return Err(Error::UnknownOpcode(val))
 			}
-			MemoryInit(VarUint32::deserialize(reader)?.into())
+			MemoryInit(seg)
 		},
 		MEMORY_DROP => MemoryDrop(VarUint32::deserialize(reader)?.into()),
 		MEMORY_FILL => {
@@ -1669,21 +1984,26 @@ fn deserialize_bulk<R: io::Read>(reader: &mut R) -> Result<Instruction, Error> {
 			MemoryFill
 		},
 		MEMORY_COPY => {
-			if u8::from(Uint8::deserialize(reader)?) != 0 {
+			if u8::from(Uint8::deserialize(reader)?) != 0 ||
+				u8::from(Uint8::deserialize(reader)?) != 0
+			{
 				return Err(Error::UnknownOpcode(val))
 			}
 			MemoryCopy
 		},
 
 		TABLE_INIT => {
+			let seg = VarUint32::deserialize(reader)?.into();
 			if u8::from(Uint8::deserialize(reader)?) != 0 {
 				return Err(Error::UnknownOpcode(val))
 			}
-			TableInit(VarUint32::deserialize(reader)?.into())
+			TableInit(seg)
 		},
 		TABLE_DROP => TableDrop(VarUint32::deserialize(reader)?.into()),
 		TABLE_COPY => {
-			if u8::from(Uint8::deserialize(reader)?) != 0 {
+			if u8::from(Uint8::deserialize(reader)?) != 0 ||
+				u8::from(Uint8::deserialize(reader)?) != 0
+			{
 				return Err(Error::UnknownOpcode(val))
 			}
 			TableCopy
@@ -1746,10 +2066,18 @@ macro_rules! bulk {
 impl Serialize for Instruction {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &Instruction {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		use self::{opcodes::*, Instruction::*};
 
-		match self {
+		match *self {
 			Unreachable => op!(writer, UNREACHABLE),
 			Nop => op!(writer, NOP),
 			Block(block_type) => op!(writer, BLOCK, {
@@ -2053,13 +2381,42 @@ impl Serialize for Instruction {
 			},
 
 			#[cfg(feature = "atomics")]
-			Atomics(a) => return a.serialize(writer),
+			Atomics(ref a) => return a.serialize(writer),
 
 			#[cfg(feature = "simd")]
-			Simd(a) => return a.serialize(writer),
+			Simd(ref a) => return a.serialize(writer),
 
 			#[cfg(feature = "bulk")]
-			Bulk(a) => return a.serialize(writer),
+			Bulk(ref a) => return a.serialize(writer),
+
+			#[cfg(feature = "exceptions")]
+			Exception(ref a) => match *a {
+				ExceptionInstruction::Try(block_type) => op!(writer, exceptions::TRY, {
+					block_type.serialize(writer)?;
+				}),
+				ExceptionInstruction::Catch(tag) => op!(writer, exceptions::CATCH, {
+					VarUint32::from(tag).serialize(writer)?;
+				}),
+				ExceptionInstruction::CatchAll => op!(writer, exceptions::CATCH_ALL),
+				ExceptionInstruction::Delegate(depth) => op!(writer, exceptions::DELEGATE, {
+					VarUint32::from(depth).serialize(writer)?;
+				}),
+				ExceptionInstruction::Throw(tag) => op!(writer, exceptions::THROW, {
+					VarUint32::from(tag).serialize(writer)?;
+				}),
+				ExceptionInstruction::Rethrow(depth) => op!(writer, exceptions::RETHROW, {
+					VarUint32::from(depth).serialize(writer)?;
+				}),
+			},
+
+			Unknown { opcode, prefix, ref immediate_bytes } => {
+				if let Some(prefix) = prefix {
+					writer.write(&[prefix])?;
+				}
+				op!(writer, opcode, {
+					writer.write(immediate_bytes)?;
+				})
+			},
 		}
 
 		Ok(())
@@ -2070,10 +2427,19 @@ impl Serialize for Instruction {
 impl Serialize for AtomicsInstruction {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+#[cfg(feature = "atomics")]
+impl Serialize for &AtomicsInstruction {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		use self::{opcodes::atomics::*, AtomicsInstruction::*};
 
-		match self {
+		match *self {
 			AtomicWake(m) => atomic!(writer, ATOMIC_WAKE, m),
 			I32AtomicWait(m) => atomic!(writer, I32_ATOMIC_WAIT, m),
 			I64AtomicWait(m) => atomic!(writer, I64_ATOMIC_WAIT, m),
@@ -2158,10 +2524,19 @@ impl Serialize for AtomicsInstruction {
 impl Serialize for SimdInstruction {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+#[cfg(feature = "simd")]
+impl Serialize for &SimdInstruction {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		use self::{opcodes::simd::*, SimdInstruction::*};
 
-		match self {
+		match *self {
 			V128Const(ref c) => simd!(writer, V128_CONST, writer.write(&c[..])?),
 			V128Load(m) => simd!(writer, V128_LOAD, MemArg::serialize(m, writer)?),
 			V128Store(m) => simd!(writer, V128_STORE, MemArg::serialize(m, writer)?),
@@ -2323,23 +2698,32 @@ impl Serialize for SimdInstruction {
 impl Serialize for BulkInstruction {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+#[cfg(feature = "bulk")]
+impl Serialize for &BulkInstruction {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		use self::{opcodes::bulk::*, BulkInstruction::*};
 
-		match self {
+		match *self {
 			MemoryInit(seg) => bulk!(writer, MEMORY_INIT, {
-				Uint8::from(0).serialize(writer)?;
 				VarUint32::from(seg).serialize(writer)?;
+				Uint8::from(0).serialize(writer)?;
 			}),
 			MemoryDrop(seg) => bulk!(writer, MEMORY_DROP, VarUint32::from(seg).serialize(writer)?),
 			MemoryFill => bulk!(writer, MEMORY_FILL, Uint8::from(0).serialize(writer)?),
-			MemoryCopy => bulk!(writer, MEMORY_COPY, Uint8::from(0).serialize(writer)?),
+			MemoryCopy => bulk!(writer, MEMORY_COPY, writer.write(&[0, 0])?),
 			TableInit(seg) => bulk!(writer, TABLE_INIT, {
-				Uint8::from(0).serialize(writer)?;
 				VarUint32::from(seg).serialize(writer)?;
+				Uint8::from(0).serialize(writer)?;
 			}),
 			TableDrop(seg) => bulk!(writer, TABLE_DROP, VarUint32::from(seg).serialize(writer)?),
-			TableCopy => bulk!(writer, TABLE_COPY, Uint8::from(0).serialize(writer)?),
+			TableCopy => bulk!(writer, TABLE_COPY, writer.write(&[0, 0])?),
 		}
 
 		Ok(())
@@ -2635,6 +3019,24 @@ impl fmt::Display for Instruction {
 
 			#[cfg(feature = "bulk")]
 			Bulk(ref i) => i.fmt(f),
+
+			#[cfg(feature = "exceptions")]
+			Exception(ref i) => match *i {
+				ExceptionInstruction::Try(BlockType::NoResult) => fmt_op!(f, "try"),
+				ExceptionInstruction::Try(BlockType::Value(value_type)) =>
+					fmt_op!(f, "try", value_type),
+				#[cfg(feature = "multi_value")]
+				ExceptionInstruction::Try(BlockType::TypeIndex(idx)) => write!(f, "try type_idx={}", idx),
+				ExceptionInstruction::Catch(tag) => fmt_op!(f, "catch", tag),
+				ExceptionInstruction::CatchAll => fmt_op!(f, "catch_all"),
+				ExceptionInstruction::Delegate(depth) => fmt_op!(f, "delegate", depth),
+				ExceptionInstruction::Throw(tag) => fmt_op!(f, "throw", tag),
+				ExceptionInstruction::Rethrow(depth) => fmt_op!(f, "rethrow", depth),
+			},
+
+			Unknown { opcode, prefix: Some(prefix), .. } =>
+				write!(f, "unknown {:#04x} {:#04x}", prefix, opcode),
+			Unknown { opcode, prefix: None, .. } => write!(f, "unknown {:#04x}", opcode),
 		}
 	}
 }
@@ -2891,11 +3293,11 @@ impl fmt::Display for BulkInstruction {
 
 		match *self {
 			MemoryInit(_) => write!(f, "memory.init"),
-			MemoryDrop(_) => write!(f, "memory.drop"),
+			MemoryDrop(_) => write!(f, "data.drop"),
 			MemoryFill => write!(f, "memory.fill"),
 			MemoryCopy => write!(f, "memory.copy"),
 			TableInit(_) => write!(f, "table.init"),
-			TableDrop(_) => write!(f, "table.drop"),
+			TableDrop(_) => write!(f, "elem.drop"),
 			TableCopy => write!(f, "table.copy"),
 		}
 	}
@@ -2905,7 +3307,15 @@ impl Serialize for Instructions {
 	type Error = Error;
 
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
-		for op in self.0.into_iter() {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &Instructions {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		for op in self.0.iter() {
 			op.serialize(writer)?;
 		}
 
@@ -2917,7 +3327,15 @@ impl Serialize for InitExpr {
 	type Error = Error;
 
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
-		for op in self.0.into_iter() {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &InitExpr {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		for op in self.0.iter() {
 			op.serialize(writer)?;
 		}
 
@@ -2977,3 +3395,269 @@ fn instructions_hashset() {
 		vec![Call(1), Block(Value(ValueType::I32)), Drop].into_iter().collect();
 	assert!(set.contains(&Drop));
 }
+
+#[cfg(feature = "exceptions")]
+#[test]
+fn try_catch_roundtrip() {
+	use self::ExceptionInstruction::*;
+
+	let bytes = [
+		0x06, 0x40, // try
+		0x08, 0x00, // throw 0
+		0x07, 0x00, // catch 0
+		0x06, 0x40, // try
+		0x09, 0x01, // rethrow 1
+		0x18, 0x00, // delegate 0
+		0x19, // catch_all
+		0x0b, // end
+		0x0b, // end
+	];
+	let instructions = super::deserialize_buffer::<Instructions>(&bytes).expect("valid hex");
+	assert_eq!(
+		instructions.elements(),
+		&[
+			Instruction::Exception(Try(BlockType::NoResult)),
+			Instruction::Exception(Throw(0)),
+			Instruction::Exception(Catch(0)),
+			Instruction::Exception(Try(BlockType::NoResult)),
+			Instruction::Exception(Rethrow(1)),
+			Instruction::Exception(Delegate(0)),
+			Instruction::Exception(CatchAll),
+			Instruction::End,
+			Instruction::End,
+		]
+	);
+	assert_eq!(super::serialize(instructions).expect("serialization failed"), bytes);
+}
+
+#[cfg(feature = "bulk")]
+#[test]
+fn memory_fill_roundtrip() {
+	use self::BulkInstruction::*;
+
+	let bytes = [
+		0x20, 0x00, // get_local 0
+		0x41, 0x00, // i32.const 0
+		0x20, 0x01, // get_local 1
+		0xfc, 0x0b, 0x00, // memory.fill
+		0x20, 0x00, // get_local 0
+		0x20, 0x01, // get_local 1
+		0x41, 0x10, // i32.const 16
+		0xfc, 0x0a, 0x00, 0x00, // memory.copy
+		0x41, 0x00, // i32.const 0
+		0x41, 0x00, // i32.const 0
+		0x41, 0x01, // i32.const 1
+		0xfc, 0x08, 0x02, 0x00, // memory.init 2
+		0xfc, 0x09, 0x02, // data.drop 2
+		0x0b, // end
+	];
+	let instructions = super::deserialize_buffer::<Instructions>(&bytes).expect("valid hex");
+	assert_eq!(instructions.elements()[3], Instruction::Bulk(MemoryFill));
+	assert_eq!(instructions.elements()[7], Instruction::Bulk(MemoryCopy));
+	assert_eq!(instructions.elements()[11], Instruction::Bulk(MemoryInit(2)));
+	assert_eq!(format!("{}", instructions.elements()[12]), "data.drop");
+	assert_eq!(super::serialize(instructions).expect("serialization failed"), bytes);
+}
+
+#[test]
+fn insert_before_calls() {
+	use self::Instruction::{Call, Drop, End, GetLocal, Nop};
+
+	let mut instructions =
+		Instructions::new(vec![GetLocal(0), Call(1), Drop, Call(2), Call(3), Drop, End]);
+	let calls: Vec<usize> = instructions
+		.elements()
+		.iter()
+		.enumerate()
+		.filter(|(_, instruction)| matches!(instruction, Call(_)))
+		.map(|(position, _)| position)
+		.collect();
+	for position in calls.into_iter().rev() {
+		instructions.insert(position, Nop).expect("insertion before `end` to succeed");
+	}
+	assert_eq!(
+		instructions.elements(),
+		&[GetLocal(0), Nop, Call(1), Drop, Nop, Call(2), Nop, Call(3), Drop, End]
+	);
+
+	assert!(instructions.insert(instructions.elements().len(), Nop).is_err());
+	assert!(instructions.insert(100, Nop).is_err());
+}
+
+#[test]
+fn splice_keeps_end() {
+	use self::Instruction::{Call, Drop, End, Nop};
+
+	let mut instructions = Instructions::new(vec![Call(1), Drop, End]);
+	instructions
+		.splice(0..2, vec![Nop, Nop, Nop])
+		.expect("splice before `end` to succeed");
+	assert_eq!(instructions.elements(), &[Nop, Nop, Nop, End]);
+
+	assert!(instructions.splice(2..4, vec![End]).is_err());
+	assert!(instructions.splice(4..4, vec![Nop]).is_err());
+	assert_eq!(instructions.elements(), &[Nop, Nop, Nop, End]);
+}
+
+#[test]
+fn strip_nops() {
+	use self::Instruction::{Block, Br, End, Nop};
+
+	let mut instructions =
+		Instructions::new(vec![Nop, Block(BlockType::NoResult), Nop, Br(0), Nop, End, Nop, End]);
+	instructions.strip_nops();
+	assert_eq!(instructions.elements(), &[Block(BlockType::NoResult), Br(0), End, End]);
+}
+
+#[cfg(feature = "simd")]
+#[test]
+fn simd_roundtrip() {
+	use self::SimdInstruction::*;
+
+	let mut bytes = vec![0xfd, 0x02]; // v128.const
+	bytes.extend(0..16u8);
+	bytes.extend_from_slice(&[
+		0x41, 0x00, // i32.const 0
+		0xfd, 0x00, 0x04, 0x10, // v128.load align=4 offset=16
+		0xfd, 0x4e, // v128.or
+		0x1a, // drop
+		0x0b, // end
+	]);
+	let instructions = super::deserialize_buffer::<Instructions>(&bytes).expect("valid hex");
+	let constant = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+	assert_eq!(instructions.elements()[0], Instruction::Simd(V128Const(Box::new(constant))));
+	assert_eq!(
+		instructions.elements()[2],
+		Instruction::Simd(V128Load(MemArg { align: 4, offset: 16 }))
+	);
+	assert_eq!(instructions.elements()[3], Instruction::Simd(V128Or));
+	assert_eq!(super::serialize(instructions).expect("serialization failed"), bytes);
+}
+
+#[cfg(feature = "atomics")]
+#[test]
+fn atomic_rmw_add_roundtrip() {
+	use self::AtomicsInstruction::*;
+
+	let bytes = [
+		0x41, 0x00, // i32.const 0
+		0x41, 0x01, // i32.const 1
+		0xfe, 0x1e, 0x02, 0x08, // i32.atomic.rmw.add align=2 offset=8
+		0x1a, // drop
+		0x0b, // end
+	];
+	let instructions = super::deserialize_buffer::<Instructions>(&bytes).expect("valid hex");
+	assert_eq!(
+		instructions.elements()[2],
+		Instruction::Atomics(I32AtomicRmwAdd(MemArg { align: 2, offset: 8 }))
+	);
+	assert_eq!(format!("{}", instructions.elements()[2]), "i32.atomic.rmw.add");
+	assert_eq!(super::serialize(instructions).expect("serialization failed"), bytes);
+}
+
+#[test]
+fn init_expr_eval_const() {
+	use self::Instruction::*;
+
+	let globals = [ConstValue::I32(7), ConstValue::F64(2.5f64.to_bits())];
+	let eval = |code| InitExpr::new(code).eval_const(&globals);
+
+	assert_eq!(eval(vec![I32Const(-1), End]).expect("constant"), ConstValue::I32(-1));
+	assert_eq!(eval(vec![I64Const(1 << 40), End]).expect("constant"), ConstValue::I64(1 << 40));
+	let f32_bits = 1.5f32.to_bits();
+	assert_eq!(eval(vec![F32Const(f32_bits), End]).expect("constant"), ConstValue::F32(f32_bits));
+	let f64_bits = 0.25f64.to_bits();
+	assert_eq!(eval(vec![F64Const(f64_bits), End]).expect("constant"), ConstValue::F64(f64_bits));
+	assert_eq!(eval(vec![GetGlobal(1), End]).expect("constant"), globals[1]);
+
+	assert!(matches!(
+		eval(vec![GetGlobal(2), End]),
+		Err(Error::GlobalIndexOutOfBounds { index: 2, len: 2 })
+	));
+	assert!(eval(vec![I32Const(1), I32Const(2), I32Add, End]).is_err());
+	assert!(eval(vec![I32Const(1)]).is_err());
+}
+
+#[test]
+fn constant_instructions() {
+	use self::Instruction::*;
+
+	for instruction in [I32Const(1), I64Const(2), F32Const(0), F64Const(0), GetGlobal(0), End] {
+		assert!(instruction.is_constant(), "{} should be constant", instruction);
+	}
+	for instruction in [GetLocal(0), SetGlobal(0), I32Add, Call(0), Nop] {
+		assert!(!instruction.is_constant(), "{} should not be constant", instruction);
+	}
+}
+
+#[test]
+fn block_nesting_limit() {
+	let depth = 20_000;
+	let mut bytes = [0x02, 0x40].repeat(depth);
+	bytes.resize(bytes.len() + depth + 1, 0x0b);
+
+	let res = super::deserialize_buffer::<Instructions>(&bytes);
+	assert!(matches!(res, Err(Error::Other("block nesting too deep"))));
+
+	let config = ParseConfig { max_block_depth: depth, ..ParseConfig::default() };
+	let instructions = Instructions::deserialize_with(&mut io::Cursor::new(&bytes), config)
+		.expect("Nesting within the limit to be accepted");
+	assert_eq!(instructions.elements().len(), 2 * depth + 1);
+}
+
+#[test]
+fn check_structure() {
+	use self::Instruction::*;
+
+	let check = |instructions: Vec<Instruction>| Instructions::new(instructions).check_structure();
+	let expect_err = |instructions: Vec<Instruction>, message: &str| match check(instructions) {
+		Err(Error::Other(msg)) => assert_eq!(msg, message),
+		other => panic!("Expected `{}`, got {:?}", message, other),
+	};
+
+	check(vec![End]).expect("Empty body is balanced");
+	check(vec![
+		Block(BlockType::NoResult),
+		Loop(BlockType::NoResult),
+		I32Const(1),
+		If(BlockType::NoResult),
+		Nop,
+		Else,
+		Nop,
+		End,
+		End,
+		End,
+		End,
+	])
+	.expect("Nested blocks are balanced");
+
+	expect_err(vec![Block(BlockType::NoResult), Nop], "Block without matching `end`");
+	expect_err(vec![Block(BlockType::NoResult), End], "Instructions are not terminated by `end`");
+	expect_err(vec![End, End], "Instructions after the final `end`");
+	expect_err(vec![Block(BlockType::NoResult), Else, End, End], "`else` outside of an `if` block");
+	expect_err(
+		vec![I32Const(1), If(BlockType::NoResult), Else, Else, End, End],
+		"`else` outside of an `if` block",
+	);
+	expect_err(vec![Else, End], "`else` outside of an `if` block");
+}
+
+#[cfg(feature = "exceptions")]
+#[test]
+fn check_structure_exceptions() {
+	use self::{ExceptionInstruction::*, Instruction::*};
+
+	let check = |instructions: Vec<Instruction>| Instructions::new(instructions).check_structure();
+	check(vec![
+		Exception(Try(BlockType::NoResult)),
+		Exception(Catch(0)),
+		Exception(CatchAll),
+		End,
+		Exception(Try(BlockType::NoResult)),
+		Exception(Delegate(0)),
+		End,
+	])
+	.expect("Try blocks are balanced");
+	assert!(check(vec![Exception(Catch(0)), End]).is_err());
+	assert!(check(vec![Block(BlockType::NoResult), Exception(Delegate(0)), End]).is_err());
+}
@@ -8,6 +8,11 @@ const PRIMITIVES_BUFFER_LENGTH: usize = 256;
 #[cfg(not(feature = "reduced-stack-buffer"))]
 const PRIMITIVES_BUFFER_LENGTH: usize = 1024;
 
+/// Upper bound on the number of entries preallocated by `CountedList` before reading them.
+///
+/// The declared count comes from the input, so it can't be trusted for large allocations.
+const COUNTED_LIST_PREALLOCATION_LIMIT: usize = 4096;
+
 /// Unsigned variable-length integer, limited to 32 bits,
 /// represented by at most 5 bytes that may contain padding 0x80 bytes.
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -56,7 +61,7 @@ impl Deserialize for VarUint32 {
 			shift += 7;
 			if (b >> 7) == 0 {
 				if shift >= 32 && (b as u8).leading_zeros() < 4 {
-					return Err(Error::InvalidVarInt32)
+					return Err(Error::InvalidVarUint32)
 				}
 				break
 			}
@@ -116,7 +121,7 @@ impl Deserialize for VarUint64 {
 			shift += 7;
 			if (b >> 7) == 0 {
 				if shift >= 64 && (b as u8).leading_zeros() < 7 {
-					return Err(Error::InvalidVarInt64)
+					return Err(Error::InvalidVarUint64)
 				}
 				break
 			}
@@ -556,9 +561,17 @@ impl Deserialize for String {
 impl Serialize for String {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Error> {
+		self.as_str().serialize(writer)
+	}
+}
+
+impl Serialize for &str {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Error> {
 		VarUint32::from(self.len()).serialize(writer)?;
-		writer.write(&self.into_bytes()[..])?;
+		writer.write(self.as_bytes())?;
 		Ok(())
 	}
 }
@@ -573,6 +586,16 @@ impl<T: Deserialize> CountedList<T> {
 	pub fn into_inner(self) -> Vec<T> {
 		self.0
 	}
+
+	/// Number of entries in the list.
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Is the list empty?
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
 }
 
 impl<T: Deserialize> Deserialize for CountedList<T>
@@ -583,7 +606,7 @@ where
 
 	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
 		let count: usize = VarUint32::deserialize(reader)?.into();
-		let mut result = Vec::new();
+		let mut result = Vec::with_capacity(count.min(COUNTED_LIST_PREALLOCATION_LIMIT));
 		for _ in 0..count {
 			result.push(T::deserialize(reader)?);
 		}
@@ -625,6 +648,25 @@ impl<'a, W: 'a + io::Write> io::Write for CountedWriter<'a, W> {
 	}
 }
 
+/// Helper struct to write a value preceded by the length of its serialized form as
+/// VarUint32, without buffering it.
+///
+/// Unlike [`CountedWriter`], the value is serialized twice: once into a sink which only
+/// counts the bytes, and then directly into the writer. The output is the same.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamedCountedWriter<T>(pub T);
+
+impl<T: Serialize<Error = elements::Error> + Copy> Serialize for StreamedCountedWriter<T> {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		let mut counter = io::CountingWriter::new();
+		self.0.serialize(&mut counter)?;
+		VarUint32::from(counter.count()).serialize(writer)?;
+		self.0.serialize(writer)
+	}
+}
+
 /// Helper struct to write series of `T` preceded by the length of the sequence
 /// serialized as VarUint32.
 #[derive(Debug, Clone)]
@@ -651,12 +693,42 @@ impl<I: Serialize<Error = elements::Error>, T: IntoIterator<Item = I>> Serialize
 	}
 }
 
+/// Helper struct to write a slice of borrowed `T` preceded by its length serialized
+/// as VarUint32, without taking ownership of the entries.
+#[derive(Debug)]
+pub struct CountedListRefWriter<'a, T>(pub &'a [T]);
+
+impl<'a, T> Clone for CountedListRefWriter<'a, T> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'a, T> Copy for CountedListRefWriter<'a, T> {}
+
+impl<'a, T> Serialize for CountedListRefWriter<'a, T>
+where
+	&'a T: Serialize<Error = elements::Error>,
+{
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		VarUint32::from(self.0.len()).serialize(writer)?;
+		for data_element in self.0 {
+			data_element.serialize(writer)?
+		}
+
+		Ok(())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 
 	use super::{
-		super::{deserialize_buffer, Serialize},
-		CountedList, VarInt32, VarInt64, VarInt7, VarUint32, VarUint64,
+		super::{deserialize_buffer, serialize, DataSegment, InitExpr, Instruction, Serialize},
+		CountedList, CountedListRefWriter, CountedListWriter, VarInt32, VarInt64, VarInt7,
+		VarUint32, VarUint64,
 	};
 	use crate::elements::Error;
 
@@ -893,6 +965,57 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn padded_within_limit() {
+		// Non-minimal encodings are valid as long as they fit in the maximum byte count.
+		let padded32 = [0x80, 0x80, 0x80, 0x80, 0x00];
+		let padded64 = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+		assert_eq!(0u32, deserialize_buffer::<VarUint32>(&padded32).expect("valid").into());
+		assert_eq!(0i32, deserialize_buffer::<VarInt32>(&padded32).expect("valid").into());
+		assert_eq!(0u64, deserialize_buffer::<VarUint64>(&padded64).expect("valid").into());
+		assert_eq!(0i64, deserialize_buffer::<VarInt64>(&padded64).expect("valid").into());
+	}
+
+	#[test]
+	fn overlong() {
+		let overlong32 = [0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+		let overlong64 = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+		assert!(matches!(
+			deserialize_buffer::<VarUint32>(&overlong32),
+			Err(Error::InvalidVarUint32)
+		));
+		assert!(matches!(deserialize_buffer::<VarInt32>(&overlong32), Err(Error::InvalidVarInt32)));
+		assert!(matches!(
+			deserialize_buffer::<VarUint64>(&overlong64),
+			Err(Error::InvalidVarUint64)
+		));
+		assert!(matches!(deserialize_buffer::<VarInt64>(&overlong64), Err(Error::InvalidVarInt64)));
+	}
+
+	#[test]
+	fn unused_bits() {
+		assert!(matches!(
+			deserialize_buffer::<VarUint32>(&[0x80, 0x80, 0x80, 0x80, 0x10]),
+			Err(Error::InvalidVarUint32)
+		));
+		assert!(matches!(
+			deserialize_buffer::<VarInt32>(&[0x80, 0x80, 0x80, 0x80, 0x10]),
+			Err(Error::InvalidVarInt32)
+		));
+		assert!(matches!(
+			deserialize_buffer::<VarUint64>(&[
+				0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x02
+			]),
+			Err(Error::InvalidVarUint64)
+		));
+		assert!(matches!(
+			deserialize_buffer::<VarInt64>(&[
+				0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x02
+			]),
+			Err(Error::InvalidVarInt64)
+		));
+	}
+
 	#[test]
 	fn varint32_max() {
 		varint32_serde_test(vec![0xff, 0xff, 0xff, 0xff, 0x07], 2147483647);
@@ -914,4 +1037,42 @@ mod tests {
 		let v3: i8 = (*vars.get(1).unwrap()).into();
 		assert_eq!(-0x03i8, v3);
 	}
+
+	#[test]
+	fn counted_list_preallocates() {
+		let mut payload = Vec::new();
+		VarUint32::from(1000u32).serialize(&mut payload).expect("to be serialized ok");
+		payload.extend_from_slice(&[0x01; 1000]);
+
+		let list: CountedList<VarInt7> =
+			deserialize_buffer(&payload).expect("list to be deserialized");
+		assert_eq!(1000, list.len());
+		assert_eq!(1000, list.into_inner().capacity());
+	}
+
+	#[test]
+	fn counted_list_absurd_count() {
+		// declares u32::MAX entries but has none
+		let payload = [0xff, 0xff, 0xff, 0xff, 0x0f];
+
+		let res = deserialize_buffer::<CountedList<VarInt7>>(&payload);
+		assert!(res.is_err());
+	}
+
+	#[test]
+	fn counted_list_ref_writer() {
+		let segments = vec![
+			DataSegment::new(0, Some(InitExpr::empty()), vec![1, 2, 3]),
+			DataSegment::new(
+				0,
+				Some(InitExpr::new(vec![Instruction::I32Const(8), Instruction::End])),
+				vec![4],
+			),
+		];
+
+		let borrowed = serialize(CountedListRefWriter(&segments)).expect("to be serialized ok");
+		let owned = serialize(CountedListWriter::<DataSegment, _>(segments.len(), segments))
+			.expect("to be serialized ok");
+		assert_eq!(borrowed, owned);
+	}
 }
@@ -312,7 +312,7 @@ impl Serialize for RelocationEntry {
 #[cfg(test)]
 mod tests {
 	use super::{
-		super::{deserialize_file, Section},
+		super::{deserialize_file, serialize, CustomSection, Module, Section},
 		RelocationEntry,
 	};
 
@@ -337,4 +337,55 @@ mod tests {
 		}
 		assert!(found, "There should be a reloc section in relocatable.wasm");
 	}
+
+	#[test]
+	fn custom_section_as_reloc() {
+		let mut module = deserialize_file("./res/cases/v1/relocatable.wasm")
+			.expect("Module should be deserialized");
+		let custom = module
+			.custom_sections()
+			.find(|custom| custom.name().starts_with("reloc."))
+			.expect("There should be a reloc section in relocatable.wasm");
+		let reloc_section = custom.as_reloc().expect("Reloc section should be parsed");
+		assert_eq!(reloc_section.entries().len(), 2);
+		assert!(CustomSection::new("linking".into(), vec![0x01]).as_reloc().is_none());
+
+		let custom = module
+			.sections_mut()
+			.iter_mut()
+			.find_map(|section| match *section {
+				Section::Custom(ref mut custom) if custom.name().starts_with("reloc.") =>
+					Some(custom),
+				_ => None,
+			})
+			.expect("There should be a reloc section in relocatable.wasm");
+		custom.payload_mut().push(0);
+		assert!(custom.as_reloc().is_none());
+		custom.set_name("renamed".into());
+
+		let module = Module::from_bytes(module.into_bytes().expect("Module should be serialized"))
+			.expect("Module should be deserialized");
+		let custom = module
+			.custom_sections()
+			.find(|custom| custom.name() == "renamed")
+			.expect("Renamed section should be present");
+		assert_eq!(custom.payload().last(), Some(&0));
+		assert!(custom.as_reloc().is_none());
+	}
+
+	#[test]
+	fn reloc_section_roundtrip() {
+		let module = deserialize_file("./res/cases/v1/relocatable.wasm")
+			.expect("Module should be deserialized");
+		let custom = module
+			.custom_sections()
+			.find(|custom| custom.name().starts_with("reloc."))
+			.expect("There should be a reloc section in relocatable.wasm");
+		let reloc_section = custom.as_reloc().expect("Reloc section should be parsed");
+
+		assert_eq!(
+			serialize(reloc_section).expect("Reloc section should be serialized"),
+			serialize(custom.clone()).expect("Custom section should be serialized")
+		);
+	}
 }
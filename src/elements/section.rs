@@ -1,13 +1,17 @@
 use super::{
-	serialize, CountedList, CountedListWriter, CountedWriter, DataSegment, Deserialize,
-	ElementSegment, Error, ExportEntry, External, Func, FuncBody, GlobalEntry, ImportEntry,
-	MemoryType, Serialize, TableType, VarUint32, VarUint7,
+	serialize, CountedList, CountedListRefWriter, CountedListWriter, CountedWriter, DataSegment,
+	Deserialize, ElementSegment, Error, ExportEntry, External, Func, FuncBody, GlobalEntry,
+	ImportEntry, MemoryType, ParseConfig, Serialize, StreamedCountedWriter, TableType, VarUint32,
+	VarUint7,
 };
 use crate::{elements, io};
 use alloc::{borrow::ToOwned, string::String, vec::Vec};
 
 use super::{name_section::NameSection, reloc_section::RelocSection, types::Type};
 
+#[cfg(feature = "exceptions")]
+use super::TagType;
+
 #[cfg(feature = "reduced-stack-buffer")]
 const ENTRIES_BUFFER_LENGTH: usize = 256;
 
@@ -36,6 +40,9 @@ pub enum Section {
 	Table(TableSection),
 	/// Memory definition section.
 	Memory(MemorySection),
+	/// Exception tags section.
+	#[cfg(feature = "exceptions")]
+	Tag(TagSection),
 	/// Global entries section.
 	Global(GlobalSection),
 	/// Export definitions.
@@ -66,6 +73,15 @@ impl Deserialize for Section {
 	type Error = Error;
 
 	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
+		Section::deserialize_with(reader, ParseConfig::default())
+	}
+}
+
+impl Section {
+	pub(crate) fn deserialize_with<R: io::Read>(
+		reader: &mut R,
+		config: ParseConfig,
+	) -> Result<Self, Error> {
 		let id = match VarUint7::deserialize(reader) {
 			// todo: be more selective detecting no more section
 			Err(_) => return Err(Error::UnexpectedEof),
@@ -82,20 +98,18 @@ impl Deserialize for Section {
 			6 => Section::Global(GlobalSection::deserialize(reader)?),
 			7 => Section::Export(ExportSection::deserialize(reader)?),
 			8 => {
-				let mut section_reader = SectionReader::new(reader)?;
-				let start_idx = VarUint32::deserialize(&mut section_reader)?;
-				section_reader.close()?;
+				let start_idx = SectionReader::parse(reader, VarUint32::deserialize)?;
 				Section::Start(start_idx.into())
 			},
 			9 => Section::Element(ElementSection::deserialize(reader)?),
-			10 => Section::Code(CodeSection::deserialize(reader)?),
+			10 => Section::Code(CodeSection::deserialize_with(reader, config)?),
 			11 => Section::Data(DataSection::deserialize(reader)?),
 			12 => {
-				let mut section_reader = SectionReader::new(reader)?;
-				let count = VarUint32::deserialize(&mut section_reader)?;
-				section_reader.close()?;
+				let count = SectionReader::parse(reader, VarUint32::deserialize)?;
 				Section::DataCount(count.into())
 			},
+			#[cfg(feature = "exceptions")]
+			13 => Section::Tag(TagSection::deserialize(reader)?),
 			invalid_id => return Err(Error::InvalidSectionId(invalid_id)),
 		})
 	}
@@ -106,75 +120,83 @@ impl Serialize for Section {
 
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		match self {
-			Section::Custom(custom_section) => {
+			Section::Name(name_section) => {
 				VarUint7::from(0x00).serialize(writer)?;
+				let custom =
+					CustomSection { name: "name".to_owned(), payload: serialize(name_section)? };
+				custom.serialize(writer)
+			},
+			Section::Reloc(reloc_section) => {
+				VarUint7::from(0x00).serialize(writer)?;
+				reloc_section.serialize(writer)
+			},
+			section => (&section).serialize(writer),
+		}
+	}
+}
+
+impl Serialize for &Section {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		VarUint7::from(self.id()).serialize(writer)?;
+		match *self {
+			Section::Custom(ref custom_section) => {
 				custom_section.serialize(writer)?;
 			},
-			Section::Unparsed { id, payload } => {
-				VarUint7::from(id).serialize(writer)?;
+			Section::Unparsed { ref payload, .. } => {
 				writer.write(&payload[..])?;
 			},
-			Section::Type(type_section) => {
-				VarUint7::from(0x01).serialize(writer)?;
+			Section::Type(ref type_section) => {
 				type_section.serialize(writer)?;
 			},
-			Section::Import(import_section) => {
-				VarUint7::from(0x02).serialize(writer)?;
+			Section::Import(ref import_section) => {
 				import_section.serialize(writer)?;
 			},
-			Section::Function(function_section) => {
-				VarUint7::from(0x03).serialize(writer)?;
+			Section::Function(ref function_section) => {
 				function_section.serialize(writer)?;
 			},
-			Section::Table(table_section) => {
-				VarUint7::from(0x04).serialize(writer)?;
+			Section::Table(ref table_section) => {
 				table_section.serialize(writer)?;
 			},
-			Section::Memory(memory_section) => {
-				VarUint7::from(0x05).serialize(writer)?;
+			Section::Memory(ref memory_section) => {
 				memory_section.serialize(writer)?;
 			},
-			Section::Global(global_section) => {
-				VarUint7::from(0x06).serialize(writer)?;
+			#[cfg(feature = "exceptions")]
+			Section::Tag(ref tag_section) => {
+				tag_section.serialize(writer)?;
+			},
+			Section::Global(ref global_section) => {
 				global_section.serialize(writer)?;
 			},
-			Section::Export(export_section) => {
-				VarUint7::from(0x07).serialize(writer)?;
+			Section::Export(ref export_section) => {
 				export_section.serialize(writer)?;
 			},
 			Section::Start(index) => {
-				VarUint7::from(0x08).serialize(writer)?;
 				let mut counted_writer = CountedWriter::new(writer);
 				VarUint32::from(index).serialize(&mut counted_writer)?;
 				counted_writer.done()?;
 			},
 			Section::DataCount(count) => {
-				VarUint7::from(0x0c).serialize(writer)?;
 				let mut counted_writer = CountedWriter::new(writer);
 				VarUint32::from(count).serialize(&mut counted_writer)?;
 				counted_writer.done()?;
 			},
-			Section::Element(element_section) => {
-				VarUint7::from(0x09).serialize(writer)?;
+			Section::Element(ref element_section) => {
 				element_section.serialize(writer)?;
 			},
-			Section::Code(code_section) => {
-				VarUint7::from(0x0a).serialize(writer)?;
+			Section::Code(ref code_section) => {
 				code_section.serialize(writer)?;
 			},
-			Section::Data(data_section) => {
-				VarUint7::from(0x0b).serialize(writer)?;
+			Section::Data(ref data_section) => {
 				data_section.serialize(writer)?;
 			},
-			Section::Name(name_section) => {
-				VarUint7::from(0x00).serialize(writer)?;
-				let custom =
-					CustomSection { name: "name".to_owned(), payload: serialize(name_section)? };
-				custom.serialize(writer)?;
+			// Name and relocation sections are only serialized by value, so they are cloned.
+			Section::Name(ref name_section) => {
+				name_section.to_custom_section()?.serialize(writer)?;
 			},
-			Section::Reloc(reloc_section) => {
-				VarUint7::from(0x00).serialize(writer)?;
-				reloc_section.serialize(writer)?;
+			Section::Reloc(ref reloc_section) => {
+				reloc_section.clone().serialize(writer)?;
 			},
 		}
 		Ok(())
@@ -182,6 +204,50 @@ impl Serialize for Section {
 }
 
 impl Section {
+	/// Serialize the section without buffering the payload of code and data sections.
+	///
+	/// Code and data sections are written through a [`StreamedCountedWriter`], which
+	/// serializes their entries twice instead of collecting them to compute the length
+	/// prefix. Other sections are serialized as usual. The output is the same in both cases.
+	pub fn serialize_streamed<W: io::Write>(self, writer: &mut W) -> Result<(), Error> {
+		match self {
+			Section::Code(code_section) => {
+				VarUint7::from(0x0a).serialize(writer)?;
+				StreamedCountedWriter(CountedListRefWriter(code_section.bodies())).serialize(writer)
+			},
+			Section::Data(data_section) => {
+				VarUint7::from(0x0b).serialize(writer)?;
+				StreamedCountedWriter(CountedListRefWriter(data_section.entries()))
+					.serialize(writer)
+			},
+			section => section.serialize(writer),
+		}
+	}
+
+	/// Id of the section in the binary format.
+	///
+	/// Custom sections, including parsed name and relocation sections, have id `0`.
+	pub fn id(&self) -> u8 {
+		match *self {
+			Section::Custom(_) | Section::Name(_) | Section::Reloc(_) => 0x00,
+			Section::Unparsed { id, .. } => id,
+			Section::Type(_) => 0x01,
+			Section::Import(_) => 0x02,
+			Section::Function(_) => 0x03,
+			Section::Table(_) => 0x04,
+			Section::Memory(_) => 0x05,
+			Section::Global(_) => 0x06,
+			Section::Export(_) => 0x07,
+			Section::Start(_) => 0x08,
+			Section::Element(_) => 0x09,
+			Section::Code(_) => 0x0a,
+			Section::Data(_) => 0x0b,
+			Section::DataCount(_) => 0x0c,
+			#[cfg(feature = "exceptions")]
+			Section::Tag(_) => 0x0d,
+		}
+	}
+
 	pub(crate) fn order(&self) -> u8 {
 		match *self {
 			Section::Custom(_) => 0x00,
@@ -191,13 +257,15 @@ impl Section {
 			Section::Function(_) => 0x3,
 			Section::Table(_) => 0x4,
 			Section::Memory(_) => 0x5,
-			Section::Global(_) => 0x6,
-			Section::Export(_) => 0x7,
-			Section::Start(_) => 0x8,
-			Section::Element(_) => 0x9,
-			Section::DataCount(_) => 0x0a,
-			Section::Code(_) => 0x0b,
-			Section::Data(_) => 0x0c,
+			#[cfg(feature = "exceptions")]
+			Section::Tag(_) => 0x6,
+			Section::Global(_) => 0x7,
+			Section::Export(_) => 0x8,
+			Section::Start(_) => 0x9,
+			Section::Element(_) => 0x0a,
+			Section::DataCount(_) => 0x0b,
+			Section::Code(_) => 0x0c,
+			Section::Data(_) => 0x0d,
 			Section::Name(_) => 0x00,
 			Section::Reloc(_) => 0x00,
 		}
@@ -207,6 +275,7 @@ impl Section {
 pub(crate) struct SectionReader {
 	cursor: io::Cursor<Vec<u8>>,
 	declared_length: usize,
+	base_offset: Option<usize>,
 }
 
 impl SectionReader {
@@ -214,16 +283,31 @@ impl SectionReader {
 		let length = u32::from(VarUint32::deserialize(reader)?) as usize;
 		let inner_buffer = buffered_read!(ENTRIES_BUFFER_LENGTH, length, reader);
 		let declared_length = inner_buffer.len();
+		let base_offset = reader.offset().map(|end| end - declared_length);
 		let cursor = io::Cursor::new(inner_buffer);
 
-		Ok(SectionReader { cursor, declared_length })
+		Ok(SectionReader { cursor, declared_length, base_offset })
 	}
 
-	pub fn close(self) -> Result<(), io::Error> {
-		let cursor = self.cursor;
-		let buf_length = self.declared_length;
+	/// Read a length-prefixed payload from `reader` and parse all of it with `parse`.
+	///
+	/// Errors raised while parsing are annotated with the offset they occurred at.
+	pub fn parse<R, T, F>(reader: &mut R, parse: F) -> Result<T, elements::Error>
+	where
+		R: io::Read,
+		F: FnOnce(&mut SectionReader) -> Result<T, elements::Error>,
+	{
+		let mut section_reader = SectionReader::new(reader)?;
+		parse(&mut section_reader)
+			.and_then(|result| {
+				section_reader.close()?;
+				Ok(result)
+			})
+			.map_err(|err| err.at(io::Read::offset(&section_reader)))
+	}
 
-		if cursor.position() != buf_length {
+	pub fn close(&self) -> Result<(), io::Error> {
+		if self.cursor.position() != self.declared_length {
 			Err(io::Error::InvalidData)
 		} else {
 			Ok(())
@@ -236,15 +320,18 @@ impl io::Read for SectionReader {
 		self.cursor.read(buf)?;
 		Ok(())
 	}
+
+	fn offset(&self) -> Option<usize> {
+		self.base_offset.map(|base| base + self.cursor.position())
+	}
 }
 
 fn read_entries<R: io::Read, T: Deserialize<Error = elements::Error>>(
 	reader: &mut R,
 ) -> Result<Vec<T>, elements::Error> {
-	let mut section_reader = SectionReader::new(reader)?;
-	let result = CountedList::<T>::deserialize(&mut section_reader)?.into_inner();
-	section_reader.close()?;
-	Ok(result)
+	SectionReader::parse(reader, |section_reader| {
+		Ok(CountedList::<T>::deserialize(section_reader)?.into_inner())
+	})
 }
 
 /// Custom section.
@@ -279,6 +366,32 @@ impl CustomSection {
 	pub fn payload_mut(&mut self) -> &mut Vec<u8> {
 		&mut self.payload
 	}
+
+	/// Rename the custom section.
+	pub fn set_name(&mut self, name: String) {
+		self.name = name;
+	}
+
+	/// Parse the payload as a relocation section.
+	///
+	/// Returns `None` if this is not a `reloc.*` section or it cannot be decoded.
+	pub fn as_reloc(&self) -> Option<RelocSection> {
+		self.parse_reloc()?.ok()
+	}
+
+	/// Parse the payload as a relocation section, if this is a `reloc.*` section.
+	pub(crate) fn parse_reloc(&self) -> Option<Result<RelocSection, Error>> {
+		if !self.name.starts_with("reloc.") {
+			return None
+		}
+		let mut rdr = io::Cursor::new(&self.payload[..]);
+		Some(RelocSection::deserialize(self.name.clone(), &mut rdr).and_then(|reloc_section| {
+			if rdr.position() != self.payload.len() {
+				return Err(io::Error::InvalidData.into())
+			}
+			Ok(reloc_section)
+		}))
+	}
 }
 
 impl Deserialize for CustomSection {
@@ -297,11 +410,19 @@ impl Deserialize for CustomSection {
 impl Serialize for CustomSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &CustomSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		use io::Write;
 
 		let mut counted_writer = CountedWriter::new(writer);
-		self.name.serialize(&mut counted_writer)?;
+		self.name.as_str().serialize(&mut counted_writer)?;
 		counted_writer.write(&self.payload[..])?;
 		counted_writer.done()?;
 		Ok(())
@@ -327,6 +448,12 @@ impl TypeSection {
 	pub fn types_mut(&mut self) -> &mut Vec<Type> {
 		&mut self.0
 	}
+
+	/// Append a type declaration and return its index.
+	pub fn push(&mut self, ty: Type) -> u32 {
+		self.0.push(ty);
+		(self.0.len() - 1) as u32
+	}
 }
 
 impl Deserialize for TypeSection {
@@ -340,12 +467,17 @@ impl Deserialize for TypeSection {
 impl Serialize for TypeSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &TypeSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<Type, _>(data.len(), data.into_iter().map(Into::into));
-		counted_list.serialize(&mut counted_writer)?;
+		CountedListRefWriter(&self.0).serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
 	}
@@ -399,12 +531,17 @@ impl Deserialize for ImportSection {
 impl Serialize for ImportSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &ImportSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<ImportEntry, _>(data.len(), data.into_iter().map(Into::into));
-		counted_list.serialize(&mut counted_writer)?;
+		CountedListRefWriter(&self.0).serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
 	}
@@ -429,6 +566,15 @@ impl FunctionSection {
 	pub fn entries(&self) -> &[Func] {
 		&self.0
 	}
+
+	/// Append a function signature and return its index in the section.
+	///
+	/// Imported functions come first in the functions space, so the index of the
+	/// function there is offset by the number of imported functions.
+	pub fn push(&mut self, func: Func) -> u32 {
+		self.0.push(func);
+		(self.0.len() - 1) as u32
+	}
 }
 
 impl Deserialize for FunctionSection {
@@ -442,12 +588,20 @@ impl Deserialize for FunctionSection {
 impl Serialize for FunctionSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &FunctionSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
+		let data = &self.0;
 		let counted_list = CountedListWriter::<VarUint32, _>(
 			data.len(),
-			data.into_iter().map(|func| func.type_ref().into()),
+			data.iter().map(|func| func.type_ref().into()),
 		);
 		counted_list.serialize(&mut counted_writer)?;
 		counted_writer.done()?;
@@ -487,11 +641,18 @@ impl Deserialize for TableSection {
 impl Serialize for TableSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &TableSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<TableType, _>(data.len(), data.into_iter().map(Into::into));
+		let data = &self.0;
+		let counted_list = CountedListWriter::<TableType, _>(data.len(), data.iter().copied());
 		counted_list.serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
@@ -530,11 +691,75 @@ impl Deserialize for MemorySection {
 impl Serialize for MemorySection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &MemorySection {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		let mut counted_writer = CountedWriter::new(writer);
+		let data = &self.0;
+		let counted_list = CountedListWriter::<MemoryType, _>(data.len(), data.iter().copied());
+		counted_list.serialize(&mut counted_writer)?;
+		counted_writer.done()?;
+		Ok(())
+	}
+}
+
+/// Exception tags definition section.
+///
+/// This is only available if the `exceptions` feature is enabled.
+#[cfg(feature = "exceptions")]
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct TagSection(Vec<TagType>);
+
+#[cfg(feature = "exceptions")]
+impl TagSection {
+	/// List of all tag entries in the section.
+	pub fn entries(&self) -> &[TagType] {
+		&self.0
+	}
+
+	/// New tag section from list of tag entries.
+	pub fn with_entries(entries: Vec<TagType>) -> Self {
+		TagSection(entries)
+	}
+
+	/// List of all tag entries in the section (mutable).
+	pub fn entries_mut(&mut self) -> &mut Vec<TagType> {
+		&mut self.0
+	}
+}
+
+#[cfg(feature = "exceptions")]
+impl Deserialize for TagSection {
+	type Error = Error;
+
+	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
+		Ok(TagSection(read_entries(reader)?))
+	}
+}
+
+#[cfg(feature = "exceptions")]
+impl Serialize for TagSection {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+#[cfg(feature = "exceptions")]
+impl Serialize for &TagSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<MemoryType, _>(data.len(), data.into_iter().map(Into::into));
+		let data = &self.0;
+		let counted_list = CountedListWriter::<TagType, _>(data.len(), data.iter().copied());
 		counted_list.serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
@@ -560,6 +785,15 @@ impl GlobalSection {
 	pub fn entries_mut(&mut self) -> &mut Vec<GlobalEntry> {
 		&mut self.0
 	}
+
+	/// Append a global entry and return its index in the section.
+	///
+	/// Imported globals come first in the globals space, so the index of the global
+	/// there is offset by the number of imported globals.
+	pub fn push(&mut self, global: GlobalEntry) -> u32 {
+		self.0.push(global);
+		(self.0.len() - 1) as u32
+	}
 }
 
 impl Deserialize for GlobalSection {
@@ -573,12 +807,17 @@ impl Deserialize for GlobalSection {
 impl Serialize for GlobalSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &GlobalSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<GlobalEntry, _>(data.len(), data.into_iter().map(Into::into));
-		counted_list.serialize(&mut counted_writer)?;
+		CountedListRefWriter(&self.0).serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
 	}
@@ -603,6 +842,35 @@ impl ExportSection {
 	pub fn entries_mut(&mut self) -> &mut Vec<ExportEntry> {
 		&mut self.0
 	}
+
+	/// Append an export entry and return its index in the section.
+	pub fn push(&mut self, entry: ExportEntry) -> u32 {
+		self.0.push(entry);
+		(self.0.len() - 1) as u32
+	}
+
+	/// Drop exports referring to entries which do not exist in `module`.
+	///
+	/// Returns the number of dropped exports.
+	pub fn retain_valid(&mut self, module: &elements::Module) -> usize {
+		let len = self.0.len();
+		self.0.retain(|entry| {
+			let (index, space) = match *entry.internal() {
+				elements::Internal::Function(index) => (index, module.functions_space()),
+				elements::Internal::Table(index) => (index, module.table_space()),
+				elements::Internal::Memory(index) => (index, module.memory_space()),
+				elements::Internal::Global(index) => (index, module.globals_space()),
+				#[cfg(feature = "exceptions")]
+				elements::Internal::Tag(index) => (
+					index,
+					module.import_count(elements::ImportCountType::Tag) +
+						module.tag_section().map(|ts| ts.entries().len()).unwrap_or(0),
+				),
+			};
+			(index as usize) < space
+		});
+		len - self.0.len()
+	}
 }
 
 impl Deserialize for ExportSection {
@@ -616,12 +884,17 @@ impl Deserialize for ExportSection {
 impl Serialize for ExportSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &ExportSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<ExportEntry, _>(data.len(), data.into_iter().map(Into::into));
-		counted_list.serialize(&mut counted_writer)?;
+		CountedListRefWriter(&self.0).serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
 	}
@@ -652,19 +925,37 @@ impl Deserialize for CodeSection {
 	type Error = Error;
 
 	fn deserialize<R: io::Read>(reader: &mut R) -> Result<Self, Self::Error> {
-		Ok(CodeSection(read_entries(reader)?))
+		CodeSection::deserialize_with(reader, ParseConfig::default())
+	}
+}
+
+impl CodeSection {
+	fn deserialize_with<R: io::Read>(reader: &mut R, config: ParseConfig) -> Result<Self, Error> {
+		SectionReader::parse(reader, |section_reader| {
+			let count: usize = VarUint32::deserialize(section_reader)?.into();
+			let mut bodies = Vec::new();
+			for _ in 0..count {
+				bodies.push(FuncBody::deserialize_with(section_reader, config)?);
+			}
+			Ok(CodeSection(bodies))
+		})
 	}
 }
 
 impl Serialize for CodeSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &CodeSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<FuncBody, _>(data.len(), data.into_iter().map(Into::into));
-		counted_list.serialize(&mut counted_writer)?;
+		CountedListRefWriter(&self.0).serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
 	}
@@ -702,12 +993,17 @@ impl Deserialize for ElementSection {
 impl Serialize for ElementSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &ElementSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<ElementSegment, _>(data.len(), data.into_iter().map(Into::into));
-		counted_list.serialize(&mut counted_writer)?;
+		CountedListRefWriter(&self.0).serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
 	}
@@ -745,12 +1041,17 @@ impl Deserialize for DataSection {
 impl Serialize for DataSection {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &DataSection {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let mut counted_writer = CountedWriter::new(writer);
-		let data = self.0;
-		let counted_list =
-			CountedListWriter::<DataSegment, _>(data.len(), data.into_iter().map(Into::into));
-		counted_list.serialize(&mut counted_writer)?;
+		CountedListRefWriter(&self.0).serialize(&mut counted_writer)?;
 		counted_writer.done()?;
 		Ok(())
 	}
@@ -766,6 +1067,7 @@ mod tests {
 		},
 		CodeSection, DataSection, ElementSection, Section, Type, TypeSection,
 	};
+	use crate::elements::Unparsed;
 
 	#[test]
 	fn import_section() {
@@ -967,6 +1269,20 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn data_section_patch_value() {
+		let mut data_section = DataSection::with_entries(vec![DataSegment::new(
+			0u32,
+			Some(InitExpr::empty()),
+			vec![0u8; 4],
+		)]);
+		data_section.entries_mut()[0].value_mut()[2] = 0x2a;
+		assert_eq!(data_section.entries()[0].value(), &[0x00, 0x00, 0x2a, 0x00]);
+
+		let buf = serialize(data_section).expect("Data section to be serialized");
+		assert_eq!(buf, vec![8u8, 0x01, 0x00, 0x0b, 4, 0x00, 0x00, 0x2a, 0x00]);
+	}
+
 	#[test]
 	fn data_section_detect() {
 		let section: Section =
@@ -1050,4 +1366,102 @@ mod tests {
 
 		assert_eq!(serialized, vec![8u8, 1u8, 0u8]);
 	}
+
+	#[test]
+	fn unparsed_with_huge_length() {
+		// declared length u32::MAX, followed by just a couple of bytes
+		let payload = [0xff, 0xff, 0xff, 0xff, 0x0f, 0x01, 0x02];
+		assert!(deserialize_buffer::<Unparsed>(&payload).is_err());
+
+		let payload = [0x02, 0x01, 0x02];
+		let unparsed = deserialize_buffer::<Unparsed>(&payload).expect("unparsed to deserialize");
+		assert_eq!(unparsed.0, vec![0x01, 0x02]);
+	}
+
+	#[test]
+	fn serialize_borrowed() {
+		let module = deserialize_file("./res/cases/v1/test5.wasm").expect("Should be deserialized");
+		for section in module.sections() {
+			assert_eq!(
+				serialize(section).expect("Should be serialized"),
+				serialize(section.clone()).expect("Should be serialized")
+			);
+			match *section {
+				Section::Global(ref globals) => assert_eq!(
+					serialize(globals).expect("Should be serialized"),
+					serialize(globals.clone()).expect("Should be serialized")
+				),
+				Section::Code(ref code) => assert_eq!(
+					serialize(code).expect("Should be serialized"),
+					serialize(code.clone()).expect("Should be serialized")
+				),
+				Section::Element(ref elements) => assert_eq!(
+					serialize(elements).expect("Should be serialized"),
+					serialize(elements.clone()).expect("Should be serialized")
+				),
+				Section::Data(ref data) => assert_eq!(
+					serialize(data).expect("Should be serialized"),
+					serialize(data.clone()).expect("Should be serialized")
+				),
+				_ => {},
+			}
+		}
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn serialize_streamed() {
+		use super::super::Instruction;
+
+		let body = FuncBody::new(
+			vec![Local::new(1, ValueType::I32)],
+			Instructions::new(vec![Instruction::I32Const(42), Instruction::Drop, Instruction::End]),
+		);
+		let segment = DataSegment::new(0, None, vec![0x2a; 100]);
+		for section in [
+			Section::Code(CodeSection::with_bodies(vec![body; 10_000])),
+			Section::Data(DataSection::with_entries(vec![segment; 1_000])),
+		] {
+			let buffered = serialize(section.clone()).expect("Section to serialize");
+			let mut cursor = std::io::Cursor::new(Vec::new());
+			section.serialize_streamed(&mut cursor).expect("Section to serialize");
+			assert_eq!(cursor.into_inner(), buffered);
+		}
+
+		let module = deserialize_file("./res/cases/v1/test5.wasm").expect("Should be deserialized");
+		let mut streamed = Vec::new();
+		module.clone().serialize_streamed(&mut streamed).expect("Module to serialize");
+		assert_eq!(streamed, serialize(module).expect("Module to serialize"));
+	}
+
+	#[test]
+	fn push_returns_index() {
+		use super::{
+			super::{
+				ExportEntry, Func, FunctionType, GlobalEntry, GlobalType, Instruction, Internal,
+			},
+			ExportSection, FunctionSection, GlobalSection,
+		};
+
+		let mut types = TypeSection::default();
+		assert_eq!(types.push(Type::Function(FunctionType::default())), 0);
+		assert_eq!(types.push(Type::Function(FunctionType::new(vec![ValueType::I32], vec![]))), 1);
+
+		let mut functions = FunctionSection::default();
+		assert_eq!(functions.push(Func::new(1)), 0);
+		assert_eq!(functions.push(Func::new(0)), 1);
+
+		let mut globals = GlobalSection::default();
+		let global = GlobalEntry::new(
+			GlobalType::new(ValueType::I32, false),
+			InitExpr::new(vec![Instruction::I32Const(0), Instruction::End]),
+		);
+		assert_eq!(globals.push(global.clone()), 0);
+		assert_eq!(globals.push(global), 1);
+		assert_eq!(globals.entries().len(), 2);
+
+		let mut exports = ExportSection::default();
+		assert_eq!(exports.push(ExportEntry::new("a".into(), Internal::Function(0))), 0);
+		assert_eq!(exports.push(ExportEntry::new("b".into(), Internal::Global(1))), 1);
+	}
 }
@@ -131,6 +131,14 @@ impl Deserialize for ElementSegment {
 impl Serialize for ElementSegment {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &ElementSegment {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		#[cfg(feature = "bulk")]
 		{
@@ -146,12 +154,12 @@ impl Serialize for ElementSegment {
 		#[cfg(not(feature = "bulk"))]
 		VarUint32::from(self.index).serialize(writer)?;
 
-		if let Some(offset) = self.offset {
+		if let Some(ref offset) = self.offset {
 			offset.serialize(writer)?;
 		}
-		let data = self.members;
+		let data = &self.members;
 		let counted_list =
-			CountedListWriter::<VarUint32, _>(data.len(), data.into_iter().map(Into::into));
+			CountedListWriter::<VarUint32, _>(data.len(), data.iter().map(|&m| m.into()));
 		counted_list.serialize(writer)?;
 		Ok(())
 	}
@@ -264,6 +272,14 @@ impl Deserialize for DataSegment {
 impl Serialize for DataSegment {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &DataSegment {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		#[cfg(feature = "bulk")]
 		{
@@ -279,11 +295,11 @@ impl Serialize for DataSegment {
 		#[cfg(not(feature = "bulk"))]
 		VarUint32::from(self.index).serialize(writer)?;
 
-		if let Some(offset) = self.offset {
+		if let Some(ref offset) = self.offset {
 			offset.serialize(writer)?;
 		}
 
-		let value = self.value;
+		let value = &self.value;
 		VarUint32::from(value.len()).serialize(writer)?;
 		writer.write(&value[..])?;
 		Ok(())
@@ -24,14 +24,22 @@ impl Serialize for Type {
 	type Error = Error;
 
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
-		match self {
-			Type::Function(fn_type) => fn_type.serialize(writer),
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &Type {
+	type Error = Error;
+
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		match *self {
+			Type::Function(ref fn_type) => fn_type.serialize(writer),
 		}
 	}
 }
 
 /// Value type.
-#[derive(Clone, Copy, Debug, PartialEq, Hash, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Hash, Eq, PartialOrd, Ord)]
 pub enum ValueType {
 	/// 32-bit signed integer
 	I32,
@@ -44,6 +52,12 @@ pub enum ValueType {
 	#[cfg(feature = "simd")]
 	/// 128-bit SIMD register
 	V128,
+	#[cfg(feature = "reference_types")]
+	/// Reference to a function
+	FuncRef,
+	#[cfg(feature = "reference_types")]
+	/// Reference to a host object
+	ExternRef,
 }
 
 impl Deserialize for ValueType {
@@ -59,6 +73,10 @@ impl Deserialize for ValueType {
 			-0x04 => Ok(ValueType::F64),
 			#[cfg(feature = "simd")]
 			-0x05 => Ok(ValueType::V128),
+			#[cfg(feature = "reference_types")]
+			-0x10 => Ok(ValueType::FuncRef),
+			#[cfg(feature = "reference_types")]
+			-0x11 => Ok(ValueType::ExternRef),
 			_ => Err(Error::UnknownValueType(val.into())),
 		}
 	}
@@ -75,6 +93,10 @@ impl Serialize for ValueType {
 			ValueType::F64 => -0x04,
 			#[cfg(feature = "simd")]
 			ValueType::V128 => -0x05,
+			#[cfg(feature = "reference_types")]
+			ValueType::FuncRef => -0x10,
+			#[cfg(feature = "reference_types")]
+			ValueType::ExternRef => -0x11,
 		}
 		.into();
 		val.serialize(writer)?;
@@ -91,6 +113,10 @@ impl fmt::Display for ValueType {
 			ValueType::F64 => write!(f, "f64"),
 			#[cfg(feature = "simd")]
 			ValueType::V128 => write!(f, "v128"),
+			#[cfg(feature = "reference_types")]
+			ValueType::FuncRef => write!(f, "funcref"),
+			#[cfg(feature = "reference_types")]
+			ValueType::ExternRef => write!(f, "externref"),
 		}
 	}
 }
@@ -121,6 +147,10 @@ impl Deserialize for BlockType {
 			-0x04 => Ok(BlockType::Value(ValueType::F64)),
 			#[cfg(feature = "simd")]
 			-0x05 => Ok(BlockType::Value(ValueType::V128)),
+			#[cfg(feature = "reference_types")]
+			-0x10 => Ok(BlockType::Value(ValueType::FuncRef)),
+			#[cfg(feature = "reference_types")]
+			-0x11 => Ok(BlockType::Value(ValueType::ExternRef)),
 			#[cfg(feature = "multi_value")]
 			idx => {
 				let idx = idx.try_into().map_err(|_| Error::UnknownBlockType(idx))?;
@@ -144,6 +174,10 @@ impl Serialize for BlockType {
 			BlockType::Value(ValueType::F64) => -0x04,
 			#[cfg(feature = "simd")]
 			BlockType::Value(ValueType::V128) => -0x05,
+			#[cfg(feature = "reference_types")]
+			BlockType::Value(ValueType::FuncRef) => -0x10,
+			#[cfg(feature = "reference_types")]
+			BlockType::Value(ValueType::ExternRef) => -0x11,
 			#[cfg(feature = "multi_value")]
 			BlockType::TypeIndex(idx) => idx as i32,
 		}
@@ -154,7 +188,7 @@ impl Serialize for BlockType {
 }
 
 /// Function signature type.
-#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+#[derive(Debug, Clone, PartialEq, Hash, Eq, PartialOrd, Ord)]
 pub struct FunctionType {
 	form: u8,
 	params: Vec<ValueType>,
@@ -192,6 +226,10 @@ impl FunctionType {
 	pub fn results_mut(&mut self) -> &mut Vec<ValueType> {
 		&mut self.results
 	}
+	/// Whether the signature has exactly the given params and results.
+	pub fn matches(&self, params: &[ValueType], results: &[ValueType]) -> bool {
+		self.params == params && self.results == results
+	}
 }
 
 impl Deserialize for FunctionType {
@@ -221,19 +259,23 @@ impl Deserialize for FunctionType {
 impl Serialize for FunctionType {
 	type Error = Error;
 
+	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
+		(&self).serialize(writer)
+	}
+}
+
+impl Serialize for &FunctionType {
+	type Error = Error;
+
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		VarUint7::from(self.form).serialize(writer)?;
 
-		let params_counted_list = CountedListWriter::<ValueType, _>(
-			self.params.len(),
-			self.params.into_iter().map(Into::into),
-		);
+		let params_counted_list =
+			CountedListWriter::<ValueType, _>(self.params.len(), self.params.iter().copied());
 		params_counted_list.serialize(writer)?;
 
-		let results_counted_list = CountedListWriter::<ValueType, _>(
-			self.results.len(),
-			self.results.into_iter().map(Into::into),
-		);
+		let results_counted_list =
+			CountedListWriter::<ValueType, _>(self.results.len(), self.results.iter().copied());
 		results_counted_list.serialize(writer)?;
 
 		Ok(())
@@ -243,8 +285,11 @@ impl Serialize for FunctionType {
 /// Table element type.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TableElementType {
-	/// A reference to a function with any signature.
+	/// A reference to a function with any signature, `funcref`.
 	AnyFunc,
+	/// A reference to a host object.
+	#[cfg(feature = "reference_types")]
+	ExternRef,
 }
 
 impl Deserialize for TableElementType {
@@ -255,6 +300,8 @@ impl Deserialize for TableElementType {
 
 		match val.into() {
 			-0x10 => Ok(TableElementType::AnyFunc),
+			#[cfg(feature = "reference_types")]
+			-0x11 => Ok(TableElementType::ExternRef),
 			_ => Err(Error::UnknownTableElementType(val.into())),
 		}
 	}
@@ -266,9 +313,66 @@ impl Serialize for TableElementType {
 	fn serialize<W: io::Write>(self, writer: &mut W) -> Result<(), Self::Error> {
 		let val: VarInt7 = match self {
 			TableElementType::AnyFunc => -0x10,
+			#[cfg(feature = "reference_types")]
+			TableElementType::ExternRef => -0x11,
 		}
 		.into();
 		val.serialize(writer)?;
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		super::{deserialize_buffer, Error},
+		FunctionType, Type, ValueType,
+	};
+
+	#[test]
+	fn function_type_hash_and_matches() {
+		let func_type =
+			FunctionType::new(vec![ValueType::I32, ValueType::I64], vec![ValueType::F32]);
+		assert!(func_type.matches(&[ValueType::I32, ValueType::I64], &[ValueType::F32]));
+		assert!(!func_type.matches(&[ValueType::I32], &[ValueType::F32]));
+		assert!(!func_type.matches(&[ValueType::I32, ValueType::I64], &[]));
+
+		let set: std::collections::HashSet<FunctionType> =
+			vec![func_type.clone(), func_type].into_iter().collect();
+		assert_eq!(set.len(), 1);
+	}
+
+	#[cfg(feature = "reference_types")]
+	#[test]
+	fn externref_table_roundtrip() {
+		use super::super::{deserialize_buffer, serialize, TableElementType, TableType};
+
+		let bytes = [0x6f, 0x01, 0x01, 0x10]; // externref, min 1, max 16
+		let table: TableType = deserialize_buffer(&bytes).expect("valid table type");
+		assert_eq!(table, TableType::with_elem_type(TableElementType::ExternRef, 1, Some(16)));
+		assert_eq!(serialize(table).expect("serialization failed"), bytes);
+
+		let value_type: ValueType = deserialize_buffer(&[0x70]).expect("valid value type");
+		assert_eq!(value_type, ValueType::FuncRef);
+		assert_eq!(format!("{}", ValueType::ExternRef), "externref");
+	}
+
+	#[test]
+	fn unknown_function_form() {
+		// form, no params, no results
+		assert_eq!(
+			deserialize_buffer::<FunctionType>(&[0x60, 0x00, 0x00])
+				.expect("Valid form")
+				.form(),
+			0x60
+		);
+		assert!(matches!(
+			deserialize_buffer::<FunctionType>(&[0x61, 0x00, 0x00]),
+			Err(Error::UnknownFunctionForm(0x61))
+		));
+		assert!(matches!(
+			deserialize_buffer::<Type>(&[0x61, 0x00, 0x00]),
+			Err(Error::UnknownFunctionForm(0x61))
+		));
+	}
+}
@@ -37,6 +37,13 @@ pub trait Read {
 	///
 	/// If there is not enough data in this read then `UnexpectedEof` will be returned.
 	fn read(&mut self, buf: &mut [u8]) -> Result<()>;
+
+	/// Number of bytes read from the start of the input, if known.
+	///
+	/// Used to report where deserialization errors occur.
+	fn offset(&self) -> Option<usize> {
+		None
+	}
 }
 
 /// Reader that saves the last position.
@@ -67,6 +74,69 @@ impl<T: AsRef<[u8]>> Read for Cursor<T> {
 		self.pos += requested;
 		Ok(())
 	}
+
+	fn offset(&self) -> Option<usize> {
+		Some(self.pos)
+	}
+}
+
+/// Writer that discards the data, only counting the bytes written.
+#[derive(Debug, Default)]
+pub struct CountingWriter {
+	count: usize,
+}
+
+impl CountingWriter {
+	pub fn new() -> Self {
+		CountingWriter::default()
+	}
+
+	/// Number of bytes written so far.
+	pub fn count(&self) -> usize {
+		self.count
+	}
+}
+
+impl Write for CountingWriter {
+	fn write(&mut self, buf: &[u8]) -> Result<()> {
+		self.count += buf.len();
+		Ok(())
+	}
+}
+
+/// Reader that counts the bytes consumed from the underlying reader.
+///
+/// Reports the count as its offset, so that errors can be located in readers which do not
+/// track their position themselves.
+#[cfg(feature = "std")]
+pub struct TrackingReader<R> {
+	inner: R,
+	consumed: usize,
+}
+
+#[cfg(feature = "std")]
+impl<R> TrackingReader<R> {
+	pub fn new(inner: R) -> Self {
+		TrackingReader { inner, consumed: 0 }
+	}
+
+	/// Number of bytes consumed so far.
+	pub fn consumed(&self) -> usize {
+		self.consumed
+	}
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for TrackingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<()> {
+		self.inner.read(buf)?;
+		self.consumed += buf.len();
+		Ok(())
+	}
+
+	fn offset(&self) -> Option<usize> {
+		Some(self.consumed())
+	}
 }
 
 #[cfg(not(feature = "std"))]
@@ -115,4 +185,28 @@ mod tests {
 		let mut buf = [0, 1, 2];
 		assert!(cursor.read(&mut buf[..]).is_err());
 	}
+
+	#[test]
+	fn counting_writer() {
+		let mut writer = CountingWriter::new();
+		assert!(writer.write(&[1, 2, 3]).is_ok());
+		assert!(writer.write(&[]).is_ok());
+		assert!(writer.write(&[4]).is_ok());
+		assert_eq!(writer.count(), 4);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn tracking_reader() {
+		let mut reader = TrackingReader::new(Cursor::new(vec![1u8, 2, 3]));
+		let mut buf = [0u8; 2];
+		assert!(reader.read(&mut buf).is_ok());
+		assert_eq!(buf, [1, 2]);
+		assert_eq!(reader.consumed(), 2);
+		assert_eq!(reader.offset(), Some(2));
+
+		// failed reads do not count
+		assert!(reader.read(&mut buf).is_err());
+		assert_eq!(reader.consumed(), 2);
+	}
 }
@@ -9,7 +9,10 @@ pub mod builder;
 pub mod elements;
 mod io;
 
-pub use elements::{deserialize_buffer, peek_size, serialize, Error as SerializationError};
+pub use elements::{
+	deserialize_buffer, deserialize_buffer_partial, deserialize_from, is_wasm, peek_size,
+	serialize, serialize_to, Error as SerializationError,
+};
 
 #[cfg(feature = "std")]
 pub use elements::{deserialize_file, serialize_to_file};